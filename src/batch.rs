@@ -0,0 +1,278 @@
+use std::{
+	collections::{HashMap, HashSet},
+	time::Duration,
+};
+
+use reqwest::{Method, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{request, GraphError, GraphErrorResponse, Identity, MSGraph, RetryEvent};
+
+/// Graph rejects `$batch` requests with more than this many parts.
+pub const MAX_BATCH_SIZE: usize = 20;
+
+///
+/// Up to 20 Graph requests sent as one POST to `/$batch`.
+///
+/// ```ignore
+/// let batch = BatchRequest::new()
+///     .request(BatchRequestItem::post("1", "/planner/buckets", json!({ "name": "Tech Work", "planId": plan.id, "orderHint": " !" })))
+///     .request(BatchRequestItem::post("2", "/planner/buckets", json!({ "name": "Design Work", "planId": plan.id, "orderHint": " !" })).depends_on("1"));
+/// let responses = graph.batch(Identity::App, batch).await?;
+/// let bucket: Bucket = responses.json("2")?;
+/// ```
+///
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BatchRequest {
+	requests: Vec<BatchRequestItem>,
+
+	#[serde(skip)]
+	beta: bool,
+}
+
+///
+/// One request inside a `BatchRequest`.
+/// `url` is relative to the version root, e.g. "/teams/{team-id}/channels".
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchRequestItem {
+	pub id: String,
+	pub method: String,
+	pub url: String,
+
+	#[serde(rename = "dependsOn", skip_serializing_if = "Vec::is_empty")]
+	pub depends_on: Vec<String>,
+
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub headers: HashMap<String, String>,
+
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub body: Option<serde_json::Value>,
+}
+
+///
+/// The answer to a `BatchRequest`, one entry per request in no particular order.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResponse {
+	pub responses: Vec<BatchResponseItem>,
+}
+
+///
+/// The answer to one `BatchRequestItem`.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResponseItem {
+	pub id: String,
+	pub status: u16,
+
+	#[serde(default)]
+	pub headers: HashMap<String, String>,
+
+	pub body: Option<serde_json::Value>,
+
+	/// Absolute url of the matching request, filled in by `MSGraph::batch` for error reporting.
+	#[serde(skip)]
+	pub url: String,
+}
+
+impl BatchRequest {
+	/// An empty batch against the configured API version.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// An empty batch against the beta endpoint; every item url is then relative to `/beta`.
+	#[must_use]
+	pub fn beta() -> Self {
+		Self { beta: true, ..Self::default() }
+	}
+
+	#[must_use]
+	pub fn request(mut self, item: BatchRequestItem) -> Self {
+		self.requests.push(item);
+		self
+	}
+
+	#[must_use]
+	pub const fn len(&self) -> usize {
+		self.requests.len()
+	}
+
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.requests.is_empty()
+	}
+}
+
+impl BatchRequestItem {
+	#[must_use]
+	pub fn new(id: &str, method: &Method, url: &str) -> Self {
+		Self { id: id.to_owned(), method: method.as_str().to_owned(), url: url.to_owned(), depends_on: Vec::new(), headers: HashMap::new(), body: None }
+	}
+
+	#[must_use]
+	pub fn get(id: &str, url: &str) -> Self {
+		Self::new(id, &Method::GET, url)
+	}
+
+	#[must_use]
+	pub fn post(id: &str, url: &str, body: serde_json::Value) -> Self {
+		Self::new(id, &Method::POST, url).body(body)
+	}
+
+	#[must_use]
+	pub fn patch(id: &str, url: &str, body: serde_json::Value) -> Self {
+		Self::new(id, &Method::PATCH, url).body(body)
+	}
+
+	#[must_use]
+	pub fn delete(id: &str, url: &str) -> Self {
+		Self::new(id, &Method::DELETE, url)
+	}
+
+	/// Set a JSON body; Graph requires the matching `Content-Type` header inside a batch.
+	#[must_use]
+	pub fn body(mut self, body: serde_json::Value) -> Self {
+		self.headers.insert("Content-Type".to_owned(), "application/json".to_owned());
+		self.body = Some(body);
+		self
+	}
+
+	#[must_use]
+	pub fn header(mut self, name: &str, value: &str) -> Self {
+		self.headers.insert(name.to_owned(), value.to_owned());
+		self
+	}
+
+	/// Run this request only after the request with `id` succeeded; Graph answers 424 otherwise.
+	#[must_use]
+	pub fn depends_on(mut self, id: &str) -> Self {
+		self.depends_on.push(id.to_owned());
+		self
+	}
+}
+
+impl BatchResponse {
+	#[must_use]
+	pub fn get(&self, id: &str) -> Option<&BatchResponseItem> {
+		self.responses.iter().find(|response| response.id == id)
+	}
+
+	/// The body of the response to `id`.
+	///
+	/// # Errors
+	/// Returns `GraphError::NotFound` if the batch had no request `id`, `GraphError::Api` if that request failed and `GraphError::Deserialize` if its body is not a `T`.
+	pub fn json<T: DeserializeOwned>(&self, id: &str) -> Result<T, GraphError> {
+		self.get(id).ok_or_else(|| GraphError::NotFound(format!("batch response {id}")))?.json()
+	}
+}
+
+impl BatchResponseItem {
+	#[must_use]
+	pub const fn is_success(&self) -> bool {
+		self.status >= 200 && self.status < 300
+	}
+
+	/// The failure of this request as a `GraphError::Api`, or `None` if it succeeded.
+	#[must_use]
+	pub fn error(&self) -> Option<GraphError> {
+		if self.is_success() {
+			return None;
+		}
+		let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+		let header = |name: &str| self.headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.clone());
+		let retry_after = header("Retry-After").and_then(|value| value.trim().parse().ok()).map(std::time::Duration::from_secs);
+		let envelope = self.body.clone().and_then(|body| serde_json::from_value::<GraphErrorResponse>(body).ok());
+		let (code, message, request_id) = envelope.map_or((None, None, None), |envelope| (envelope.error.code, envelope.error.message, envelope.error.inner_error.and_then(|inner| inner.request_id)));
+		Some(GraphError::Api { status, url: self.url.clone(), code, message, request_id: request_id.or_else(|| header("request-id")), retry_after })
+	}
+
+	/// The body of this response.
+	///
+	/// # Errors
+	/// Returns `GraphError::Api` if the request failed and `GraphError::Deserialize` if the body is not a `T`.
+	pub fn json<T: DeserializeOwned>(&self) -> Result<T, GraphError> {
+		if let Some(err) = self.error() {
+			return Err(err);
+		}
+		serde_json::from_value(self.body.clone().unwrap_or(serde_json::Value::Null)).map_err(|source| GraphError::Deserialize { url: self.url.clone(), source })
+	}
+}
+
+impl MSGraph {
+	/// Send up to 20 requests in one round-trip.
+	/// A failed part does not fail the call; check each `BatchResponseItem`.
+	///
+	/// Graph throttles each part on its own. Parts the retry policy would retry are sent again in a smaller batch after the longest `Retry-After`, together with the parts that failed with 424 because they depend on them.
+	/// A throttled (429) part was not run, so it is retried whatever its method; other retryable failures only for methods `RetryPolicy::allows`.
+	///
+	/// # Errors
+	/// Returns `GraphError::BatchTooLarge` for more than 20 requests and `GraphError::Api` if Graph rejects the batch as a whole.
+	pub async fn batch(&self, identity: Identity<'_>, batch: BatchRequest) -> Result<BatchResponse, GraphError> {
+		if batch.len() > MAX_BATCH_SIZE {
+			return Err(GraphError::BatchTooLarge(batch.len()));
+		}
+
+		let root = if batch.beta { self.config.beta_url("") } else { self.config.url("") };
+		let mut response = self.send_batch(identity, &root, &batch).await?;
+
+		let mut retries = 0;
+		loop {
+			retries += 1;
+			let Some((pending, delay, status)) = self.retryable_parts(&batch, &response, retries) else { return Ok(response) };
+
+			self.config.retry.notify(&RetryEvent { method: Method::POST, url: format!("{root}/$batch"), retry: retries, delay, status });
+			tokio::time::sleep(delay).await;
+
+			let requests = batch.requests.iter().filter(|request| pending.contains(&request.id)).cloned().map(|mut request| {
+				request.depends_on.retain(|id| pending.contains(id));
+				request
+			});
+			let resent = self.send_batch(identity, &root, &BatchRequest { requests: requests.collect(), beta: batch.beta }).await?;
+			response.responses.retain(|item| !pending.contains(&item.id));
+			response.responses.extend(resent.responses);
+		}
+	}
+
+	async fn send_batch(&self, identity: Identity<'_>, root: &str, batch: &BatchRequest) -> Result<BatchResponse, GraphError> {
+		let res = self.send(self.client.post(format!("{root}/$batch")).json(&json!(batch)).bearer_auth(self.bearer_token(identity).await?)).await?;
+		let mut response = request::json::<BatchResponse>(res).await?;
+
+		for item in &mut response.responses {
+			if let Some(request) = batch.requests.iter().find(|request| request.id == item.id) {
+				item.url = format!("{root}{}", request.url);
+			}
+		}
+		Ok(response)
+	}
+
+	/// The IDs of the parts to send again for retry number `retry`, the delay before doing so and the status that caused it, or `None` if nothing is worth retrying.
+	fn retryable_parts(&self, batch: &BatchRequest, response: &BatchResponse, retry: u32) -> Option<(HashSet<String>, Duration, Option<StatusCode>)> {
+		let policy = &self.config.retry;
+		let mut pending = HashSet::new();
+		let mut longest: Option<(Duration, Option<StatusCode>)> = None;
+		for item in &response.responses {
+			let (Some(err), Some(request)) = (item.error(), batch.requests.iter().find(|request| request.id == item.id)) else { continue };
+			let throttled = err.status() == Some(StatusCode::TOO_MANY_REQUESTS);
+			let repeatable = throttled || Method::from_bytes(request.method.as_bytes()).is_ok_and(|method| policy.allows(&method));
+			let Some(delay) = policy.delay(&err, retry).filter(|_| repeatable) else { continue };
+			pending.insert(item.id.clone());
+			if longest.is_none_or(|(longest, _)| delay > longest) {
+				longest = Some((delay, err.status()));
+			}
+		}
+		let (delay, status) = longest?;
+
+		// Parts that failed only because a part they depend on is retried run again with it.
+		loop {
+			let dependents = response.responses.iter().filter(|item| item.status == StatusCode::FAILED_DEPENDENCY.as_u16() && !pending.contains(&item.id)).filter(|item| batch.requests.iter().any(|request| request.id == item.id && request.depends_on.iter().any(|id| pending.contains(id)))).map(|item| item.id.clone()).collect::<Vec<_>>();
+			if dependents.is_empty() {
+				return Some((pending, delay, status));
+			}
+			pending.extend(dependents);
+		}
+	}
+}
@@ -0,0 +1,60 @@
+use std::time::{Duration, SystemTime};
+
+use eggersmann_app_server_auth::MSAccessToken;
+use futures::future::BoxFuture;
+
+/// Refresh this long before Entra ID says the token lapses, so in-flight requests never carry an expired token.
+const EXPIRY_MARGIN: Duration = Duration::from_mins(5);
+
+///
+/// What a cached token was issued for.
+/// `user` is `None` for app tokens and a hash of the user's incoming access token for delegated tokens; `scopes` are sorted.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TokenCacheKey {
+	pub tenant_id: String,
+	pub client_id: String,
+	pub user: Option<String>,
+	pub scopes: Vec<String>,
+}
+
+///
+/// A token together with the moment it stops being usable.
+///
+#[derive(Clone)]
+pub struct CachedToken {
+	pub token: MSAccessToken,
+	pub expires_at: SystemTime,
+}
+
+///
+/// A persistent backend behind the in-memory token cache, e.g. Redis or a file, so restarts and other instances reuse tokens.
+/// Only consulted when the in-memory cache has no fresh token.
+///
+/// The in-memory cache forgets expired tokens, but never tells the store to; expire entries there yourself, e.g. with a TTL up to `CachedToken::expires_at`.
+/// Delegated tokens are keyed per incoming access token, so expect one entry per user session rather than per user.
+///
+pub trait TokenStore: Send + Sync {
+	fn load<'a>(&'a self, key: &'a TokenCacheKey) -> BoxFuture<'a, Option<CachedToken>>;
+
+	fn save<'a>(&'a self, key: &'a TokenCacheKey, token: &'a CachedToken) -> BoxFuture<'a, ()>;
+}
+
+impl TokenCacheKey {
+	/// Sort and deduplicate `scopes` so the same set always maps to the same key.
+	#[must_use]
+	pub fn new(tenant_id: &str, client_id: &str, user: Option<String>, scopes: &[String]) -> Self {
+		let mut scopes = scopes.to_vec();
+		scopes.sort();
+		scopes.dedup();
+		Self { tenant_id: tenant_id.to_owned(), client_id: client_id.to_owned(), user, scopes }
+	}
+}
+
+impl CachedToken {
+	/// Whether the token is valid for longer than the refresh margin.
+	#[must_use]
+	pub fn is_fresh(&self) -> bool {
+		SystemTime::now() + EXPIRY_MARGIN < self.expires_at
+	}
+}
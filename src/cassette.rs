@@ -0,0 +1,376 @@
+use std::{
+	collections::BTreeMap,
+	fmt::{self, Write},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex, PoisonError},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use percent_encoding::percent_decode_str;
+use reqwest::{header::CONTENT_TYPE, Client, Request, Response, ResponseBuilderExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::GraphError;
+
+/// Written in place of bearer tokens, client secrets and assertions.
+pub const REDACTED: &str = "REDACTED";
+
+/// Written in place of tenant IDs.
+pub const CASSETTE_TENANT_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Written in place of the app's client ID.
+pub const CASSETTE_CLIENT_ID: &str = "00000000-0000-0000-0000-000000000001";
+
+/// JSON fields and form parameters whose values are always redacted.
+const SECRET_FIELDS: [&str; 7] = ["access_token", "refresh_token", "id_token", "client_secret", "client_assertion", "assertion", "password"];
+
+/// JSON fields that hold a tenant ID.
+const TENANT_FIELDS: [&str; 2] = ["tenantId", "tid"];
+
+/// Response headers kept in a cassette; the rest are noise or describe the original connection.
+const KEPT_HEADERS: [&str; 4] = ["content-type", "retry-after", "location", "etag"];
+
+///
+/// Record-and-replay for every HTTP exchange of an `MSGraph`, set with `MSGraphConfig::cassette`.
+///
+/// Recording sends requests as usual and writes each exchange to a JSON file. Bearer tokens, secrets, tenant IDs, the client ID and email addresses are scrubbed first.
+/// Replaying answers requests from that file without touching the network, matching method and scrubbed url in recorded order.
+///
+/// ```ignore
+/// // Once, against a real tenant:
+/// let config = MSGraphConfig::default().cassette(Cassette::record("tests/cassettes/team.json"));
+/// // In CI:
+/// let config = MSGraphConfig::default().cassette(Cassette::replay("tests/cassettes/team.json")?);
+/// ```
+///
+#[derive(Clone)]
+pub struct Cassette {
+	path: PathBuf,
+	mode: CassetteMode,
+	state: Arc<tokio::sync::Mutex<CassetteState>>,
+	scrubber: Arc<Mutex<Scrubber>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+	Record,
+	Replay,
+}
+
+///
+/// The file a `Cassette` reads and writes.
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CassetteFile {
+	pub interactions: Vec<Interaction>,
+}
+
+///
+/// One request and the response Graph sent for it, both scrubbed.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+	pub request: InteractionRequest,
+	pub response: InteractionResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractionRequest {
+	pub method: String,
+	pub url: String,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub body: Option<InteractionBody>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractionResponse {
+	pub status: u16,
+
+	#[serde(default)]
+	pub headers: BTreeMap<String, String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub body: Option<InteractionBody>,
+}
+
+///
+/// A body as it is stored: JSON inline, other text as is, anything else (e.g. a photo) base64 encoded.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InteractionBody {
+	Json(Value),
+	Text(String),
+	Base64(String),
+}
+
+#[derive(Default)]
+struct CassetteState {
+	interactions: Vec<Interaction>,
+
+	/// Which interactions a replay has answered with already.
+	used: Vec<bool>,
+}
+
+#[derive(Default)]
+struct Scrubber {
+	replacements: Vec<(String, String)>,
+	emails: Vec<String>,
+}
+
+impl Cassette {
+	/// Record every exchange to `path`, replacing the file. It is rewritten after each exchange, so a failing test still leaves what happened up to the failure.
+	#[must_use]
+	pub fn record(path: impl Into<PathBuf>) -> Self {
+		Self::new(path.into(), CassetteMode::Record, CassetteFile::default())
+	}
+
+	/// Answer every request from the cassette at `path`.
+	///
+	/// # Errors
+	/// Returns `GraphError::Cassette` if the file cannot be read or is not a cassette.
+	pub fn replay(path: impl Into<PathBuf>) -> Result<Self, GraphError> {
+		let path = path.into();
+		let json = std::fs::read(&path).map_err(|e| cassette_error(&path, e))?;
+		let file = serde_json::from_slice::<CassetteFile>(&json).map_err(|e| cassette_error(&path, e))?;
+		Ok(Self::new(path, CassetteMode::Replay, file))
+	}
+
+	fn new(path: PathBuf, mode: CassetteMode, file: CassetteFile) -> Self {
+		let used = vec![false; file.interactions.len()];
+		let state = CassetteState { interactions: file.interactions, used };
+		Self { path, mode, state: Arc::new(tokio::sync::Mutex::new(state)), scrubber: Arc::new(Mutex::new(Scrubber::default())) }
+	}
+
+	/// Also write `placeholder` wherever `value` appears, e.g. a customer's domain or a user's name.
+	#[must_use]
+	pub fn scrub(self, value: &str, placeholder: &str) -> Self {
+		self.add_scrub(value, placeholder);
+		self
+	}
+
+	pub(crate) fn add_scrub(&self, value: &str, placeholder: &str) {
+		if value.is_empty() {
+			return;
+		}
+		let mut scrubber = self.scrubber.lock().unwrap_or_else(PoisonError::into_inner);
+		if !scrubber.replacements.iter().any(|(existing, _)| existing == value) {
+			scrubber.replacements.push((value.to_owned(), placeholder.to_owned()));
+		}
+	}
+
+	#[must_use]
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	#[must_use]
+	pub const fn mode(&self) -> CassetteMode {
+		self.mode
+	}
+
+	/// The exchanges recorded or loaded so far.
+	pub async fn interactions(&self) -> Vec<Interaction> {
+		self.state.lock().await.interactions.clone()
+	}
+
+	/// Send `request` through `client` and record the exchange, or answer it from the cassette.
+	pub(crate) async fn execute(&self, client: &Client, request: Request) -> Result<Response, GraphError> {
+		match self.mode {
+			CassetteMode::Record => self.record_exchange(client, request).await,
+			CassetteMode::Replay => self.replay_exchange(&request).await,
+		}
+	}
+
+	async fn record_exchange(&self, client: &Client, request: Request) -> Result<Response, GraphError> {
+		let url = request.url().clone();
+		let recorded_request = self.scrub_request(&request);
+		let res = client.execute(request).await.map_err(|source| GraphError::Request { url: url.to_string(), source })?;
+		let status = res.status();
+		let headers = res.headers().clone();
+		let body = res.bytes().await.map_err(|source| GraphError::Request { url: url.to_string(), source })?.to_vec();
+
+		let content_type = headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok());
+		let recorded_headers = headers.iter().filter(|(name, _)| KEPT_HEADERS.contains(&name.as_str())).filter_map(|(name, value)| Some((name.to_string(), self.scrub_text(value.to_str().ok()?)))).collect();
+		let recorded_body = self.scrub_body(&body, content_type);
+		let interaction = Interaction { request: recorded_request, response: InteractionResponse { status: status.as_u16(), headers: recorded_headers, body: recorded_body } };
+
+		let mut state = self.state.lock().await;
+		state.interactions.push(interaction);
+		state.used.push(true);
+		let json = serde_json::to_vec_pretty(&CassetteFile { interactions: state.interactions.clone() }).map_err(|e| cassette_error(&self.path, e))?;
+		if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+			tokio::fs::create_dir_all(parent).await.map_err(|e| cassette_error(&self.path, e))?;
+		}
+		tokio::fs::write(&self.path, json).await.map_err(|e| cassette_error(&self.path, e))?;
+		drop(state);
+
+		let mut response = http::Response::builder().status(status).url(url).body(body).map_err(|e| cassette_error(&self.path, e))?;
+		*response.headers_mut() = headers;
+		Ok(response.into())
+	}
+
+	async fn replay_exchange(&self, request: &Request) -> Result<Response, GraphError> {
+		let method = request.method().as_str();
+		let url = self.scrub_url(request.url().as_str());
+
+		let mut state = self.state.lock().await;
+		let CassetteState { interactions, used } = &mut *state;
+		let Some((interaction, used)) = interactions.iter().zip(used.iter_mut()).find(|(interaction, used)| !**used && interaction.request.method == method && interaction.request.url == url) else {
+			return Err(cassette_error(&self.path, format!("no unused recorded interaction for {method} {url}")));
+		};
+		*used = true;
+		let recorded = interaction.response.clone();
+		drop(state);
+
+		let mut response = http::Response::builder().status(recorded.status).url(request.url().clone());
+		for (name, value) in &recorded.headers {
+			response = response.header(name, value);
+		}
+		let body = match recorded.body {
+			None => Vec::new(),
+			Some(InteractionBody::Json(value)) => value.to_string().into_bytes(),
+			Some(InteractionBody::Text(text)) => text.into_bytes(),
+			Some(InteractionBody::Base64(encoded)) => STANDARD.decode(encoded).map_err(|e| cassette_error(&self.path, e))?,
+		};
+		Ok(response.body(body).map_err(|e| cassette_error(&self.path, e))?.into())
+	}
+
+	fn scrub_request(&self, request: &Request) -> InteractionRequest {
+		let content_type = request.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok());
+		let body = request.body().and_then(reqwest::Body::as_bytes).and_then(|body| self.scrub_body(body, content_type));
+		InteractionRequest { method: request.method().to_string(), url: self.scrub_url(request.url().as_str()), body }
+	}
+
+	fn scrub_body(&self, body: &[u8], content_type: Option<&str>) -> Option<InteractionBody> {
+		if body.is_empty() {
+			return None;
+		}
+		let mut scrubber = self.scrubber.lock().unwrap_or_else(PoisonError::into_inner);
+		if let Ok(mut json) = serde_json::from_slice::<Value>(body) {
+			scrubber.json(&mut json);
+			return Some(InteractionBody::Json(json));
+		}
+		let Ok(text) = std::str::from_utf8(body) else { return Some(InteractionBody::Base64(STANDARD.encode(body))) };
+		if content_type.is_some_and(|content_type| content_type.starts_with("application/x-www-form-urlencoded")) {
+			return Some(InteractionBody::Text(scrubber.form(text)));
+		}
+		Some(InteractionBody::Text(scrubber.text(text)))
+	}
+
+	fn scrub_text(&self, text: &str) -> String {
+		self.scrubber.lock().unwrap_or_else(PoisonError::into_inner).text(text)
+	}
+
+	/// Scrub a url in its decoded form, so percent-encoded addresses in paths, `$filter` and `$search` are caught too.
+	/// Cassettes store the decoded url; replay decodes the same way before matching.
+	fn scrub_url(&self, url: &str) -> String {
+		self.scrub_text(&percent_decode_str(url).decode_utf8_lossy())
+	}
+}
+
+impl fmt::Debug for Cassette {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Cassette").field("path", &self.path).field("mode", &self.mode).finish_non_exhaustive()
+	}
+}
+
+impl Scrubber {
+	/// Replace registered values, JWTs and email addresses in `text`.
+	fn text(&mut self, text: &str) -> String {
+		let mut text = text.to_owned();
+		for (value, placeholder) in &self.replacements {
+			text = text.replace(value, placeholder);
+		}
+		self.emails(&redact_jwts(&text))
+	}
+
+	fn json(&mut self, value: &mut Value) {
+		match value {
+			Value::String(text) => *text = self.text(text),
+			Value::Array(items) => items.iter_mut().for_each(|item| self.json(item)),
+			Value::Object(fields) => {
+				for (name, field) in fields.iter_mut() {
+					if SECRET_FIELDS.contains(&name.as_str()) && !field.is_null() {
+						*field = Value::String(REDACTED.to_owned());
+					} else if TENANT_FIELDS.contains(&name.as_str()) && field.is_string() {
+						*field = Value::String(CASSETTE_TENANT_ID.to_owned());
+					} else {
+						self.json(field);
+					}
+				}
+			}
+			Value::Null | Value::Bool(_) | Value::Number(_) => {}
+		}
+	}
+
+	/// Redact secret parameters of a form body, e.g. a token request, and scrub the others in their decoded form.
+	fn form(&mut self, body: &str) -> String {
+		body.split('&')
+			.map(|pair| match pair.split_once('=') {
+				Some((name, _)) if SECRET_FIELDS.contains(&name) => format!("{name}={REDACTED}"),
+				Some((name, value)) => format!("{name}={}", self.text(&percent_decode_str(&value.replace('+', " ")).decode_utf8_lossy())),
+				None => self.text(pair),
+			})
+			.collect::<Vec<_>>()
+			.join("&")
+	}
+
+	/// Replace every email address with `userN@example.com`, numbered by first appearance so the same address keeps the same placeholder.
+	fn emails(&mut self, text: &str) -> String {
+		let is_local = |c: char| c.is_ascii_alphanumeric() || "._%+-".contains(c);
+		let is_domain = |c: char| c.is_ascii_alphanumeric() || ".-".contains(c);
+
+		let mut scrubbed = String::with_capacity(text.len());
+		let mut rest = text;
+		while let Some(at) = rest.find('@') {
+			let start = rest[..at].char_indices().rev().find(|(_, c)| !is_local(*c)).map_or(0, |(i, c)| i + c.len_utf8());
+			let end = rest[at + 1..].find(|c| !is_domain(c)).map_or(rest.len(), |i| at + 1 + i);
+			let domain = rest[at + 1..end].trim_end_matches(['.', '-']);
+			let end = at + 1 + domain.len();
+			let is_email = start < at && domain.rsplit_once('.').is_some_and(|(host, tld)| !host.is_empty() && tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()));
+			if !is_email {
+				scrubbed.push_str(&rest[..=at]);
+				rest = &rest[at + 1..];
+				continue;
+			}
+
+			let email = rest[start..end].to_ascii_lowercase();
+			let index = self.emails.iter().position(|known| *known == email).unwrap_or_else(|| {
+				self.emails.push(email);
+				self.emails.len() - 1
+			});
+			scrubbed.push_str(&rest[..start]);
+			let _ = write!(scrubbed, "user{}@example.com", index + 1);
+			rest = &rest[end..];
+		}
+		scrubbed.push_str(rest);
+		scrubbed
+	}
+}
+
+/// Replace JSON web tokens (three base64url segments starting with `eyJ`) with `REDACTED`.
+fn redact_jwts(text: &str) -> String {
+	let is_token = |c: char| c.is_ascii_alphanumeric() || "-_.".contains(c);
+
+	let mut redacted = String::with_capacity(text.len());
+	let mut rest = text;
+	while let Some(start) = rest.find("eyJ") {
+		let end = rest[start..].find(|c| !is_token(c)).map_or(rest.len(), |i| start + i);
+		redacted.push_str(&rest[..start]);
+		if rest[start..end].split('.').filter(|segment| !segment.is_empty()).count() >= 2 {
+			redacted.push_str(REDACTED);
+		} else {
+			redacted.push_str(&rest[start..end]);
+		}
+		rest = &rest[end..];
+	}
+	redacted.push_str(rest);
+	redacted
+}
+
+fn cassette_error(path: &Path, source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> GraphError {
+	GraphError::Cassette { path: path.display().to_string(), source: source.into() }
+}
@@ -0,0 +1,124 @@
+use std::{
+	fmt,
+	path::Path,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{
+	engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+	Engine,
+};
+use openssl::{
+	hash::MessageDigest,
+	pkcs12::Pkcs12,
+	pkey::{PKey, Private},
+	rsa::Padding,
+	sign::{RsaPssSaltlen, Signer},
+	x509::X509,
+};
+use serde_json::json;
+
+use crate::GraphError;
+
+/// Lifetime of a client assertion; Entra ID only needs it for the token request it is sent with.
+const ASSERTION_LIFETIME_SECS: u64 = 600;
+
+///
+/// An X.509 certificate and its RSA private key, registered on the app in place of a client secret.
+/// `MSGraph` signs a short-lived JWT `client_assertion` with it for every token request.
+///
+#[derive(Clone)]
+pub struct ClientCertificate {
+	certificate: X509,
+	key: PKey<Private>,
+}
+
+impl ClientCertificate {
+	/// Load a certificate and key from PEM. Both may be the same buffer, e.g. a PEM file holding the certificate and an unencrypted key.
+	///
+	/// # Errors
+	/// Returns `GraphError::Credentials` if either PEM cannot be parsed.
+	pub fn from_pem(certificate_pem: &[u8], key_pem: &[u8]) -> Result<Self, GraphError> {
+		let certificate = X509::from_pem(certificate_pem).map_err(invalid)?;
+		let key = PKey::private_key_from_pem(key_pem).map_err(invalid)?;
+		Ok(Self { certificate, key })
+	}
+
+	/// Load a certificate and key from a PKCS#12 (`.pfx`/`.p12`) archive.
+	///
+	/// # Errors
+	/// Returns `GraphError::Credentials` if the archive cannot be parsed, the password is wrong, or it lacks a certificate or key.
+	pub fn from_pkcs12(der: &[u8], password: &str) -> Result<Self, GraphError> {
+		let parsed = Pkcs12::from_der(der).and_then(|archive| archive.parse2(password)).map_err(invalid)?;
+		match (parsed.cert, parsed.pkey) {
+			(Some(certificate), Some(key)) => Ok(Self { certificate, key }),
+			_ => Err(GraphError::Credentials { name: "client certificate".to_owned(), source: "PKCS#12 archive has no certificate or no private key".into() }),
+		}
+	}
+
+	/// Load a certificate and key from PEM files; `key_path` may equal `certificate_path`.
+	///
+	/// # Errors
+	/// Returns `GraphError::Credentials` if a file cannot be read or parsed.
+	pub async fn from_pem_files(certificate_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> Result<Self, GraphError> {
+		Self::from_pem(&read(certificate_path.as_ref()).await?, &read(key_path.as_ref()).await?)
+	}
+
+	/// Load a certificate and key from a PKCS#12 file.
+	///
+	/// # Errors
+	/// Returns `GraphError::Credentials` if the file cannot be read or parsed.
+	pub async fn from_pkcs12_file(path: impl AsRef<Path>, password: &str) -> Result<Self, GraphError> {
+		Self::from_pkcs12(&read(path.as_ref()).await?, password)
+	}
+
+	/// Load a certificate and key the way Azure Key Vault exports them as a secret: PEM text or a base64 encoded PKCS#12 archive without password.
+	///
+	/// # Errors
+	/// Returns `GraphError::Credentials` if the value is neither.
+	pub fn from_key_vault_secret(value: &str) -> Result<Self, GraphError> {
+		if value.trim_start().starts_with("-----BEGIN") {
+			return Self::from_pem(value.as_bytes(), value.as_bytes());
+		}
+		let der = STANDARD.decode(value.trim()).map_err(|e| GraphError::Credentials { name: "client certificate".to_owned(), source: Box::new(e) })?;
+		Self::from_pkcs12(&der, "")
+	}
+
+	/// The base64url SHA-256 thumbprint Entra ID uses to pick the registered certificate (`x5t#S256`).
+	///
+	/// # Errors
+	/// Returns `GraphError::Credentials` if the certificate cannot be hashed.
+	pub fn thumbprint(&self) -> Result<String, GraphError> {
+		let digest = self.certificate.digest(MessageDigest::sha256()).map_err(invalid)?;
+		Ok(URL_SAFE_NO_PAD.encode(digest))
+	}
+
+	/// A signed client assertion for `client_id`, valid for ten minutes at the token endpoint `audience`.
+	pub(crate) fn assertion(&self, client_id: &str, audience: &str) -> Result<String, GraphError> {
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+		let jti: [u8; 16] = rand::random();
+		let header = json!({ "alg": "PS256", "typ": "JWT", "x5t#S256": self.thumbprint()? });
+		let claims = json!({ "aud": audience, "iss": client_id, "sub": client_id, "jti": URL_SAFE_NO_PAD.encode(jti), "nbf": now, "iat": now, "exp": now + ASSERTION_LIFETIME_SECS });
+		let signing_input = format!("{}.{}", URL_SAFE_NO_PAD.encode(header.to_string()), URL_SAFE_NO_PAD.encode(claims.to_string()));
+
+		let mut signer = Signer::new(MessageDigest::sha256(), &self.key).map_err(invalid)?;
+		signer.set_rsa_padding(Padding::PKCS1_PSS).map_err(invalid)?;
+		signer.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH).map_err(invalid)?;
+		let signature = signer.sign_oneshot_to_vec(signing_input.as_bytes()).map_err(invalid)?;
+		Ok(format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(signature)))
+	}
+}
+
+impl fmt::Debug for ClientCertificate {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ClientCertificate").field("subject", &self.certificate.subject_name()).field("thumbprint", &self.thumbprint().ok()).finish_non_exhaustive()
+	}
+}
+
+fn invalid(source: openssl::error::ErrorStack) -> GraphError {
+	GraphError::Credentials { name: "client certificate".to_owned(), source: Box::new(source) }
+}
+
+async fn read(path: &Path) -> Result<Vec<u8>, GraphError> {
+	tokio::fs::read(path).await.map_err(|e| GraphError::Credentials { name: path.display().to_string(), source: Box::new(e) })
+}
@@ -1,10 +1,9 @@
 #![allow(renamed_and_removed_lints)]
 
-use rocket::{response::Responder, FromForm};
 use serde::{Deserialize, Serialize};
 
 use crate::plan::CreatePlanForm;
-use crate::Team;
+use crate::{GraphCollection, Team};
 
 ///
 /// Graph API channel object.
@@ -65,21 +64,13 @@ pub struct CreateChannelBody {
 ///
 /// Channel Collection
 ///
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ChannelCollection {
-	#[serde(rename = "@odata.context")]
-	pub odata_context: String,
-
-	#[serde(rename(serialize = "@odata.count"))]
-	pub odata_count: Option<i32>,
-
-	pub value: Vec<Channel>,
-}
+pub type ChannelCollection = GraphCollection<Channel>;
 
 ///
 /// The input form for creating a shared channel
 ///
-#[derive(FromForm, Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "rocket", derive(rocket::FromForm))]
 pub struct CreateSharedChannelForm {
 	pub team_name: String,
 	pub channel_display_name: String,
@@ -93,7 +84,6 @@ pub struct CreateSharedChannelForm {
 /// The response from creating a shared channel
 ///
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(crate = "rocket::serde")]
 pub struct CreateSharedChannelResponse {
 	pub channel: Option<Channel>,
 	pub team: Option<Team>,
@@ -102,7 +92,8 @@ pub struct CreateSharedChannelResponse {
 	pub status: String,
 }
 
-impl<'r> Responder<'r, 'static> for CreateSharedChannelResponse {
+#[cfg(feature = "rocket")]
+impl<'r> rocket::response::Responder<'r, 'static> for CreateSharedChannelResponse {
 	fn respond_to(self, req: &'r rocket::Request<'_>) -> rocket::response::Result<'static> {
 		rocket::serde::json::Json(self).respond_to(req)
 	}
@@ -0,0 +1,55 @@
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{request, GraphError, Identity, MSGraph, ODataQuery};
+
+///
+/// One page of a Graph collection.
+/// `odata_next_link` is set while more pages follow.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphCollection<T> {
+	#[serde(rename = "@odata.context")]
+	pub odata_context: Option<String>,
+
+	#[serde(rename = "@odata.nextLink")]
+	pub odata_next_link: Option<String>,
+
+	#[serde(rename = "@odata.count")]
+	pub odata_count: Option<i64>,
+
+	#[serde(default = "Vec::new")]
+	pub value: Vec<T>,
+}
+
+impl MSGraph {
+	/// Stream the pages of the collection at `url` as `identity`, following `@odata.nextLink` until the last page.
+	/// `url` is absolute, e.g. from `MSGraphConfig::url`; `query` is applied to the first page and carried on by Graph in each next link.
+	pub fn pages<'a, T: DeserializeOwned + 'a>(&'a self, identity: Identity<'a>, url: &str, query: &ODataQuery) -> impl Stream<Item = Result<GraphCollection<T>, GraphError>> + 'a {
+		let eventual = query.needs_eventual_consistency();
+		stream::try_unfold(Some(query.apply(url)), move |next| async move {
+			let Some(url) = next else { return Ok(None) };
+			let mut request = self.client.get(url).bearer_auth(self.bearer_token(identity).await?);
+			if eventual {
+				request = request.header("ConsistencyLevel", "eventual");
+			}
+			let res = self.send(request).await?;
+			let page = request::json::<GraphCollection<T>>(res).await?;
+			let next = page.odata_next_link.clone();
+			Ok(Some((page, next)))
+		})
+	}
+
+	/// Stream every item of the collection at `url` across all of its pages.
+	pub fn items<'a, T: DeserializeOwned + 'a>(&'a self, identity: Identity<'a>, url: &str, query: &ODataQuery) -> impl Stream<Item = Result<T, GraphError>> + 'a {
+		self.pages(identity, url, query).map_ok(|page| stream::iter(page.value).map(Ok)).try_flatten()
+	}
+
+	/// Collect every item of the collection at `url` across all of its pages.
+	///
+	/// # Errors
+	/// Returns the first `GraphError` hit while fetching a page.
+	pub async fn collect_all<T: DeserializeOwned>(&self, identity: Identity<'_>, url: &str, query: &ODataQuery) -> Result<Vec<T>, GraphError> {
+		self.items(identity, url, query).try_collect().await
+	}
+}
@@ -0,0 +1,142 @@
+use std::{fmt, sync::Arc};
+
+use crate::{Cassette, RetryPolicy, TokenStore};
+
+///
+/// Endpoints used by `MSGraph`.
+/// Defaults to the global Azure cloud; point `graph_root` and `authority` at a local server for tests or at a national cloud.
+///
+#[derive(Clone)]
+pub struct MSGraphConfig {
+	/// Graph root without a trailing slash. (e.g. `https://graph.microsoft.com`)
+	pub graph_root: String,
+
+	/// Token authority without a trailing slash. (e.g. `https://login.microsoftonline.com`)
+	pub authority: String,
+
+	/// Version segment used for stable endpoints. (e.g. "v1.0")
+	pub api_version: String,
+
+	/// How throttled and transiently failing requests are retried.
+	pub retry: RetryPolicy,
+
+	/// Scopes requested for the app token, as accepted by `MSGraphConfig::scope`. (default ".default", i.e. every application permission granted to the app)
+	pub app_scopes: Vec<String>,
+
+	/// Persistent backend for cached tokens; tokens are only kept in memory when `None`.
+	pub token_store: Option<Arc<dyn TokenStore>>,
+
+	/// Records Graph exchanges to, or replays them from, a file instead of only talking to Graph.
+	pub cassette: Option<Cassette>,
+}
+
+impl Default for MSGraphConfig {
+	fn default() -> Self {
+		Self {
+			graph_root: "https://graph.microsoft.com".to_owned(),
+			authority: "https://login.microsoftonline.com".to_owned(),
+			api_version: "v1.0".to_owned(),
+			retry: RetryPolicy::default(),
+			app_scopes: vec![".default".to_owned()],
+			token_store: None,
+			cassette: None,
+		}
+	}
+}
+
+impl fmt::Debug for MSGraphConfig {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MSGraphConfig").field("graph_root", &self.graph_root).field("authority", &self.authority).field("api_version", &self.api_version).field("retry", &self.retry).field("app_scopes", &self.app_scopes).field("token_store", &self.token_store.is_some()).field("cassette", &self.cassette).finish()
+	}
+}
+
+impl MSGraphConfig {
+	/// Endpoints for the Azure US Government cloud.
+	#[must_use]
+	pub fn us_government() -> Self {
+		Self { graph_root: "https://graph.microsoft.us".to_owned(), authority: "https://login.microsoftonline.us".to_owned(), ..Self::default() }
+	}
+
+	/// Endpoints for Azure China operated by 21Vianet.
+	#[must_use]
+	pub fn china() -> Self {
+		Self { graph_root: "https://microsoftgraph.chinacloudapi.cn".to_owned(), authority: "https://login.chinacloudapi.cn".to_owned(), ..Self::default() }
+	}
+
+	#[must_use]
+	pub fn graph_root(mut self, graph_root: &str) -> Self {
+		graph_root.trim_end_matches('/').clone_into(&mut self.graph_root);
+		self
+	}
+
+	#[must_use]
+	pub fn authority(mut self, authority: &str) -> Self {
+		authority.trim_end_matches('/').clone_into(&mut self.authority);
+		self
+	}
+
+	#[must_use]
+	pub fn api_version(mut self, api_version: &str) -> Self {
+		api_version.trim_matches('/').clone_into(&mut self.api_version);
+		self
+	}
+
+	#[must_use]
+	pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+		self.retry = retry;
+		self
+	}
+
+	/// Replace the scopes requested for the app token.
+	#[must_use]
+	pub fn app_scopes(mut self, scopes: &[&str]) -> Self {
+		self.app_scopes = scopes.iter().map(|scope| (*scope).to_owned()).collect();
+		self
+	}
+
+	#[must_use]
+	pub fn token_store(mut self, store: impl TokenStore + 'static) -> Self {
+		self.token_store = Some(Arc::new(store));
+		self
+	}
+
+	#[must_use]
+	pub fn cassette(mut self, cassette: Cassette) -> Self {
+		self.cassette = Some(cassette);
+		self
+	}
+
+	/// Url of a stable endpoint. `path` starts with a slash. (e.g. "/me")
+	#[must_use]
+	pub fn url(&self, path: &str) -> String {
+		format!("{}/{}{}", self.graph_root, self.api_version, path)
+	}
+
+	/// Url of a beta endpoint. `path` starts with a slash. (e.g. "/planner/plans")
+	#[must_use]
+	pub fn beta_url(&self, path: &str) -> String {
+		format!("{}/beta{}", self.graph_root, path)
+	}
+
+	/// The v2.0 token endpoint of a tenant.
+	#[must_use]
+	pub fn token_url(&self, tenant_id: &str) -> String {
+		format!("{}/{}/oauth2/v2.0/token", self.authority, tenant_id)
+	}
+
+	/// A Graph permission as a full scope. (e.g. "Group.ReadWrite.All" becomes `https://graph.microsoft.com/Group.ReadWrite.All`)
+	/// Scopes that already name a resource and `openid`, `profile`, `email` and `offline_access` are returned unchanged.
+	#[must_use]
+	pub fn scope(&self, scope: &str) -> String {
+		if scope.contains('/') || matches!(scope, "openid" | "profile" | "email" | "offline_access") {
+			return scope.to_owned();
+		}
+		format!("{}/{scope}", self.graph_root)
+	}
+
+	/// The `.default` scope of the configured Graph root.
+	#[must_use]
+	pub fn default_scope(&self) -> String {
+		format!("{}/.default", self.graph_root)
+	}
+}
@@ -0,0 +1,244 @@
+use std::{
+	fmt,
+	future::Future,
+	path::{Path, PathBuf},
+};
+
+use azure_security_keyvault::KeyvaultClient;
+use serde::Deserialize;
+
+use crate::{ClientCertificate, GraphError};
+
+///
+/// The app registration `MSGraph` authenticates as.
+/// Also a `CredentialProvider` that hands out these literal values, e.g. in tests.
+///
+#[derive(Debug, Clone)]
+pub struct AppCredentials {
+	pub tenant_id: String,
+	pub client_id: String,
+	pub credential: ClientCredential,
+}
+
+///
+/// How the app proves its identity to the token endpoint.
+///
+#[derive(Clone)]
+pub enum ClientCredential {
+	/// A client secret, sent as `client_secret`.
+	Secret(String),
+
+	/// A certificate registered on the app, used to sign a `client_assertion`.
+	Certificate(ClientCertificate),
+}
+
+///
+/// A source of app credentials for `MSGraph`.
+/// Implement it to read the app registration from somewhere the built-in providers do not cover.
+///
+pub trait CredentialProvider: Send + Sync {
+	/// Load the tenant ID, client ID and client secret.
+	///
+	/// # Errors
+	/// Returns `GraphError::Credentials` or `GraphError::KeyVault` if the credentials cannot be read.
+	fn credentials(&self) -> impl Future<Output = Result<AppCredentials, GraphError>> + Send;
+}
+
+///
+/// Reads the credentials from environment variables, by default `MS_GRAPH_TENANT_ID`, `MS_GRAPH_CLIENT_ID` and `MS_GRAPH_CLIENT_SECRET`.
+///
+#[derive(Debug, Clone)]
+pub struct EnvCredentials {
+	pub tenant_id_var: String,
+	pub client_id_var: String,
+	pub client_secret_var: String,
+}
+
+///
+/// Reads the credentials from a JSON file with the fields `tenant_id`, `client_id` and either `client_secret` or `certificate`.
+///
+/// `certificate` is the path of a PEM file holding certificate and key, or of a PKCS#12 archive (`.pfx`/`.p12`) unlocked with `certificate_password`.
+/// Relative paths are resolved against the directory of the JSON file.
+///
+#[derive(Debug, Clone)]
+pub struct FileCredentials {
+	pub path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct CredentialsFile {
+	tenant_id: String,
+	client_id: String,
+	client_secret: Option<String>,
+	certificate: Option<PathBuf>,
+	certificate_password: Option<String>,
+}
+
+///
+/// Reads the credentials from secrets in an Azure Key Vault, authenticating with `azure_identity::create_credential`.
+///
+#[derive(Debug, Clone)]
+pub struct KeyVaultCredentials {
+	/// The name of the Azure Key Vault that contains the secrets. (e.g. "eggappserverkeyvault")
+	pub key_vault_name: String,
+
+	/// The name of the secret that contains the client ID. (e.g. "ms-auth-client-id")
+	pub client_id_key: String,
+
+	/// The name of the secret that contains the tenant ID. (e.g. "ms-auth-tenant-id")
+	pub tenant_id_key: String,
+
+	/// Where the client secret or certificate is stored.
+	pub credential: KeyVaultClientCredential,
+}
+
+///
+/// The Key Vault object that holds the app's client credential.
+///
+#[derive(Debug, Clone)]
+pub enum KeyVaultClientCredential {
+	/// The name of the secret that contains the client secret. (e.g. "ms-auth-client-secret")
+	Secret(String),
+
+	/// The name of a Key Vault certificate with an exportable key. It is read through the secret of the same name, which holds the certificate and key.
+	Certificate(String),
+}
+
+impl AppCredentials {
+	#[must_use]
+	pub fn new(tenant_id: &str, client_id: &str, client_secret: &str) -> Self {
+		Self { tenant_id: tenant_id.to_owned(), client_id: client_id.to_owned(), credential: ClientCredential::Secret(client_secret.to_owned()) }
+	}
+
+	#[must_use]
+	pub fn with_certificate(tenant_id: &str, client_id: &str, certificate: ClientCertificate) -> Self {
+		Self { tenant_id: tenant_id.to_owned(), client_id: client_id.to_owned(), credential: ClientCredential::Certificate(certificate) }
+	}
+}
+
+impl fmt::Debug for ClientCredential {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Secret(_) => f.write_str("Secret(<redacted>)"),
+			Self::Certificate(certificate) => f.debug_tuple("Certificate").field(certificate).finish(),
+		}
+	}
+}
+
+impl CredentialProvider for AppCredentials {
+	async fn credentials(&self) -> Result<Self, GraphError> {
+		Ok(self.clone())
+	}
+}
+
+impl Default for EnvCredentials {
+	fn default() -> Self {
+		Self { tenant_id_var: "MS_GRAPH_TENANT_ID".to_owned(), client_id_var: "MS_GRAPH_CLIENT_ID".to_owned(), client_secret_var: "MS_GRAPH_CLIENT_SECRET".to_owned() }
+	}
+}
+
+impl EnvCredentials {
+	/// Read the default variables.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Read the given variables instead of the defaults.
+	#[must_use]
+	pub fn vars(tenant_id_var: &str, client_id_var: &str, client_secret_var: &str) -> Self {
+		Self { tenant_id_var: tenant_id_var.to_owned(), client_id_var: client_id_var.to_owned(), client_secret_var: client_secret_var.to_owned() }
+	}
+
+	fn var(name: &str) -> Result<String, GraphError> {
+		std::env::var(name).map_err(|e| GraphError::Credentials { name: name.to_owned(), source: Box::new(e) })
+	}
+}
+
+impl CredentialProvider for EnvCredentials {
+	async fn credentials(&self) -> Result<AppCredentials, GraphError> {
+		Ok(AppCredentials::new(&Self::var(&self.tenant_id_var)?, &Self::var(&self.client_id_var)?, &Self::var(&self.client_secret_var)?))
+	}
+}
+
+impl FileCredentials {
+	#[must_use]
+	pub fn new(path: impl AsRef<Path>) -> Self {
+		Self { path: path.as_ref().to_owned() }
+	}
+}
+
+impl CredentialProvider for FileCredentials {
+	async fn credentials(&self) -> Result<AppCredentials, GraphError> {
+		let name = self.path.display().to_string();
+		let contents = tokio::fs::read(&self.path).await.map_err(|e| GraphError::Credentials { name: name.clone(), source: Box::new(e) })?;
+		let file = serde_json::from_slice::<CredentialsFile>(&contents).map_err(|e| GraphError::Credentials { name: name.clone(), source: Box::new(e) })?;
+
+		let credential = match (file.client_secret, file.certificate) {
+			(Some(client_secret), None) => ClientCredential::Secret(client_secret),
+			(None, Some(certificate)) => {
+				let certificate = self.path.parent().map_or_else(|| certificate.clone(), |dir| dir.join(&certificate));
+				let is_pkcs12 = certificate.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("pfx") || extension.eq_ignore_ascii_case("p12"));
+				if is_pkcs12 {
+					ClientCredential::Certificate(ClientCertificate::from_pkcs12_file(&certificate, file.certificate_password.as_deref().unwrap_or_default()).await?)
+				} else {
+					ClientCredential::Certificate(ClientCertificate::from_pem_files(&certificate, &certificate).await?)
+				}
+			}
+			_ => return Err(GraphError::Credentials { name, source: "expected exactly one of client_secret and certificate".into() }),
+		};
+		Ok(AppCredentials { tenant_id: file.tenant_id, client_id: file.client_id, credential })
+	}
+}
+
+impl KeyVaultCredentials {
+	/// Authenticate with the client secret stored in `client_secret_key`.
+	#[must_use]
+	pub fn new(key_vault_name: &str, client_id_key: &str, client_secret_key: &str, tenant_id_key: &str) -> Self {
+		Self {
+			key_vault_name: key_vault_name.to_owned(),
+			client_id_key: client_id_key.to_owned(),
+			tenant_id_key: tenant_id_key.to_owned(),
+			credential: KeyVaultClientCredential::Secret(client_secret_key.to_owned()),
+		}
+	}
+
+	/// Authenticate with the Key Vault certificate `certificate_name` instead of a client secret.
+	#[must_use]
+	pub fn with_certificate(key_vault_name: &str, client_id_key: &str, certificate_name: &str, tenant_id_key: &str) -> Self {
+		Self {
+			key_vault_name: key_vault_name.to_owned(),
+			client_id_key: client_id_key.to_owned(),
+			tenant_id_key: tenant_id_key.to_owned(),
+			credential: KeyVaultClientCredential::Certificate(certificate_name.to_owned()),
+		}
+	}
+}
+
+impl CredentialProvider for KeyVaultCredentials {
+	async fn credentials(&self) -> Result<AppCredentials, GraphError> {
+		let azure_credentials = azure_identity::create_credential().map_err(|e| GraphError::KeyVault { name: self.key_vault_name.clone(), source: Box::new(e) })?;
+		let azure_key_vault_client = KeyvaultClient::new(&format!("https://{}.vault.azure.net", self.key_vault_name), azure_credentials).map_err(|e| GraphError::KeyVault { name: self.key_vault_name.clone(), source: Box::new(e) })?.secret_client();
+
+		// Get the secrets from the Azure Key Vault.
+		let client_id = match azure_key_vault_client.get(&self.client_id_key).await {
+			Ok(client_id) => client_id.value,
+			Err(e) => return Err(GraphError::KeyVault { name: self.client_id_key.clone(), source: Box::new(e) }),
+		};
+		let (KeyVaultClientCredential::Secret(credential_key) | KeyVaultClientCredential::Certificate(credential_key)) = &self.credential;
+		let credential = match azure_key_vault_client.get(credential_key).await {
+			Ok(credential) => credential.value,
+			Err(e) => return Err(GraphError::KeyVault { name: credential_key.clone(), source: Box::new(e) }),
+		};
+		let tenant_id = match azure_key_vault_client.get(&self.tenant_id_key).await {
+			Ok(tenant_id) => tenant_id.value,
+			Err(e) => return Err(GraphError::KeyVault { name: self.tenant_id_key.clone(), source: Box::new(e) }),
+		};
+
+		let credential = match self.credential {
+			KeyVaultClientCredential::Secret(_) => ClientCredential::Secret(credential),
+			KeyVaultClientCredential::Certificate(_) => ClientCredential::Certificate(ClientCertificate::from_key_vault_secret(&credential)?),
+		};
+		Ok(AppCredentials { tenant_id, client_id, credential })
+	}
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::GraphCollection;
+
 #[derive(Deserialize, Debug, Clone, Serialize)]
 pub struct Drive {
 	#[serde(rename = "createdDateTime")]
@@ -43,9 +45,4 @@ pub struct DriveQuota {
 	pub used: Option<i64>,
 }
 
-#[derive(Deserialize, Debug, Clone, Serialize)]
-pub struct DriveCollection {
-	#[serde(rename = "@odata.context")]
-	pub odata_context: Option<String>,
-	pub value: Vec<Drive>,
-}
+pub type DriveCollection = GraphCollection<Drive>;
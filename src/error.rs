@@ -0,0 +1,173 @@
+use std::{error::Error, fmt, time::Duration};
+
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::MAX_BATCH_SIZE;
+
+///
+/// Error returned by every `MSGraph` operation.
+///
+#[derive(Debug)]
+pub enum GraphError {
+	/// Graph answered with a non-success status code.
+	/// `retry_after` is set when Graph sent a `Retry-After` header, typically with 429 and 503.
+	Api { status: StatusCode, url: String, code: Option<String>, message: Option<String>, request_id: Option<String>, retry_after: Option<Duration> },
+
+	/// The request could not be sent or its response could not be read.
+	Request { url: String, source: reqwest::Error },
+
+	/// The response body did not have the expected shape.
+	Deserialize { url: String, source: serde_json::Error },
+
+	/// Creating the Azure credential or reading a secret from Azure Key Vault failed.
+	KeyVault { name: String, source: Box<dyn Error + Send + Sync> },
+
+	/// A `CredentialProvider` could not read a credential; `name` is the variable or file it tried.
+	Credentials { name: String, source: Box<dyn Error + Send + Sync> },
+
+	/// A lookup by name matched nothing.
+	NotFound(String),
+
+	/// A lookup by name matched several objects.
+	Ambiguous { what: String, count: usize },
+
+	/// Graph returned an object without a field the operation depends on.
+	MissingField(&'static str),
+
+	/// A `$batch` was built with more requests than Graph accepts.
+	BatchTooLarge(usize),
+
+	/// A `Cassette` could not be read or written, or has no recorded answer for a request.
+	Cassette { path: String, source: Box<dyn Error + Send + Sync> },
+}
+
+impl GraphError {
+	/// Build a `GraphError::Api` from a non-success response, decoding Graph's `{"error": {...}}` envelope (or the login endpoint's OAuth error) when present.
+	pub(crate) async fn from_response(url: String, res: Response) -> Self {
+		let status = res.status();
+		let header_request_id = res.headers().get("request-id").and_then(|value| value.to_str().ok()).map(ToOwned::to_owned);
+		// Graph sends delta-seconds; the HTTP-date form is not used by Graph and is ignored.
+		let retry_after = res.headers().get(RETRY_AFTER).and_then(|value| value.to_str().ok()).and_then(|value| value.trim().parse().ok()).map(Duration::from_secs);
+		let body = match res.text().await {
+			Ok(body) => body,
+			Err(source) => return Self::Request { url, source },
+		};
+
+		if let Ok(envelope) = serde_json::from_str::<GraphErrorResponse>(&body) {
+			let request_id = envelope.error.inner_error.and_then(|inner| inner.request_id).or(header_request_id);
+			return Self::Api { status, url, code: envelope.error.code, message: envelope.error.message, request_id, retry_after };
+		}
+
+		if let Ok(oauth) = serde_json::from_str::<OAuthErrorResponse>(&body) {
+			return Self::Api { status, url, code: Some(oauth.error), message: oauth.error_description, request_id: oauth.trace_id.or(header_request_id), retry_after };
+		}
+
+		let message = if body.trim().is_empty() { None } else { Some(body) };
+		Self::Api { status, url, code: None, message, request_id: header_request_id, retry_after }
+	}
+
+	/// The HTTP status Graph answered with, if the error came from a response.
+	#[must_use]
+	pub fn status(&self) -> Option<StatusCode> {
+		match self {
+			Self::Api { status, .. } => Some(*status),
+			Self::Request { source, .. } => source.status(),
+			_ => None,
+		}
+	}
+
+	/// The URL of the request that failed, if any.
+	#[must_use]
+	pub fn url(&self) -> Option<&str> {
+		match self {
+			Self::Api { url, .. } | Self::Request { url, .. } | Self::Deserialize { url, .. } => Some(url),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for GraphError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Api { status, url, code, message, request_id, .. } => {
+				write!(f, "Graph request to {url} failed with {status}")?;
+				if let Some(code) = code {
+					write!(f, ": {code}")?;
+				}
+				if let Some(message) = message {
+					write!(f, ": {message}")?;
+				}
+				if let Some(request_id) = request_id {
+					write!(f, " (request-id: {request_id})")?;
+				}
+				Ok(())
+			}
+			Self::Request { url, source } => write!(f, "Error sending request to {url}: {source}"),
+			Self::Deserialize { url, source } => write!(f, "Error deserializing response from {url}: {source}"),
+			Self::KeyVault { name, source } => write!(f, "Azure Key Vault error for {name}: {source}"),
+			Self::Credentials { name, source } => write!(f, "Could not read credentials from {name}: {source}"),
+			Self::NotFound(what) => write!(f, "No matching {what} found."),
+			Self::Ambiguous { what, count } => write!(f, "{count} objects match {what}; rename them or look them up by ID."),
+			Self::MissingField(field) => write!(f, "{field} not found"),
+			Self::BatchTooLarge(size) => write!(f, "A $batch holds at most {MAX_BATCH_SIZE} requests, got {size}"),
+			Self::Cassette { path, source } => write!(f, "Cassette {path}: {source}"),
+		}
+	}
+}
+
+impl Error for GraphError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::Request { source, .. } => Some(source),
+			Self::Deserialize { source, .. } => Some(source),
+			Self::KeyVault { source, .. } | Self::Credentials { source, .. } | Self::Cassette { source, .. } => Some(source.as_ref()),
+			_ => None,
+		}
+	}
+}
+
+///
+/// Graph's standard error envelope: `{"error": {...}}`.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphErrorResponse {
+	pub error: GraphErrorBody,
+}
+
+///
+/// The `error` object of Graph's error envelope.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphErrorBody {
+	pub code: Option<String>,
+	pub message: Option<String>,
+
+	#[serde(rename = "innerError")]
+	pub inner_error: Option<GraphInnerError>,
+}
+
+///
+/// The `innerError` object of Graph's error envelope.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphInnerError {
+	#[serde(rename = "request-id")]
+	pub request_id: Option<String>,
+
+	#[serde(rename = "client-request-id")]
+	pub client_request_id: Option<String>,
+
+	pub date: Option<String>,
+}
+
+///
+/// The error body returned by the login endpoint: `{"error": "invalid_client", ...}`.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthErrorResponse {
+	pub error: String,
+	pub error_description: Option<String>,
+	pub trace_id: Option<String>,
+	pub correlation_id: Option<String>,
+}
@@ -0,0 +1,102 @@
+use rocket::{
+	fairing::AdHoc,
+	http::Status,
+	request::{FromRequest, Outcome},
+	serde::Deserialize,
+	Request,
+};
+
+use crate::{GraphError, KeyVaultCredentials, MSGraph, MSGraphConfig};
+
+///
+/// The `ms_graph` table read by `MSGraph::fairing`, e.g. in `Rocket.toml`:
+///
+/// ```toml
+/// [default.ms_graph]
+/// key_vault_name = "eggappserverkeyvault"
+/// client_id_key = "ms-auth-client-id"
+/// client_secret_key = "ms-auth-client-secret"
+/// tenant_id_key = "ms-auth-tenant-id"
+/// ```
+///
+/// Set `certificate_name` instead of `client_secret_key` to authenticate with a Key Vault certificate.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct MSGraphSettings {
+	pub key_vault_name: String,
+	pub client_id_key: String,
+	pub client_secret_key: Option<String>,
+	pub certificate_name: Option<String>,
+	pub tenant_id_key: String,
+
+	/// Overrides `MSGraphConfig::graph_root`, e.g. for a national cloud.
+	pub graph_root: Option<String>,
+
+	/// Overrides `MSGraphConfig::authority`.
+	pub authority: Option<String>,
+}
+
+/// What `MSGraph::fairing` puts in managed state; an error is kept so handlers can answer 503 instead of Rocket refusing to launch.
+struct ManagedGraph(Result<MSGraph, GraphError>);
+
+impl MSGraphSettings {
+	/// The credential provider these settings describe.
+	///
+	/// # Errors
+	/// Returns `GraphError::MissingField` unless exactly one of `client_secret_key` and `certificate_name` is set.
+	pub fn credentials(&self) -> Result<KeyVaultCredentials, GraphError> {
+		match (&self.client_secret_key, &self.certificate_name) {
+			(Some(client_secret_key), None) => Ok(KeyVaultCredentials::new(&self.key_vault_name, &self.client_id_key, client_secret_key, &self.tenant_id_key)),
+			(None, Some(certificate_name)) => Ok(KeyVaultCredentials::with_certificate(&self.key_vault_name, &self.client_id_key, certificate_name, &self.tenant_id_key)),
+			_ => Err(GraphError::MissingField("ms_graph.client_secret_key or ms_graph.certificate_name")),
+		}
+	}
+
+	#[must_use]
+	pub fn config(&self) -> MSGraphConfig {
+		let mut config = MSGraphConfig::default();
+		if let Some(graph_root) = &self.graph_root {
+			config = config.graph_root(graph_root);
+		}
+		if let Some(authority) = &self.authority {
+			config = config.authority(authority);
+		}
+		config
+	}
+
+	async fn build(self) -> Result<MSGraph, GraphError> {
+		MSGraph::with_config(self.config(), &self.credentials()?).await
+	}
+}
+
+impl MSGraph {
+	/// A fairing that builds an `MSGraph` from the `ms_graph` configuration table on ignite and manages it, making `&MSGraph` available as a request guard.
+	/// Rocket still launches if authentication fails; the guard then answers 503 and the error is logged.
+	#[must_use]
+	pub fn fairing() -> AdHoc {
+		AdHoc::on_ignite("MSGraph", |rocket| async {
+			let graph = match rocket.figment().extract_inner::<MSGraphSettings>("ms_graph") {
+				Ok(settings) => settings.build().await,
+				Err(e) => Err(GraphError::Credentials { name: "ms_graph".to_owned(), source: Box::new(e) }),
+			};
+			if let Err(err) = &graph {
+				rocket::error!("Microsoft Graph is unavailable: {err}");
+			}
+			rocket.manage(ManagedGraph(graph))
+		})
+	}
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r MSGraph {
+	type Error = &'r GraphError;
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		match request.rocket().state::<ManagedGraph>() {
+			Some(ManagedGraph(Ok(graph))) => Outcome::Success(graph),
+			Some(ManagedGraph(Err(err))) => Outcome::Error((Status::ServiceUnavailable, err)),
+			None => Outcome::Forward(Status::InternalServerError),
+		}
+	}
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::GraphCollection;
+
 ///
 /// Graph API group object.
 ///
@@ -129,11 +131,4 @@ pub struct WritebackConfiguration {
 ///
 /// Graph API group collection object.
 ///
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct GroupCollection {
-	#[serde(rename = "@odata.context")]
-	pub odata_context: String,
-
-	#[serde(rename = "value")]
-	pub value: Vec<Group>,
-}
+pub type GroupCollection = GraphCollection<Group>;
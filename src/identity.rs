@@ -0,0 +1,38 @@
+use crate::{GraphError, MSGraph, User};
+
+///
+/// Who an operation runs as.
+/// Tenant policies may block app-only calls, e.g. creating shared channels; run those as a signed-in user instead.
+///
+/// ```ignore
+/// graph.automation_channels(Identity::App, team_id, &ODataQuery::new()).await?;
+/// graph.automation_channels(Identity::User(&user), team_id, &ODataQuery::new()).await?;
+/// graph.automation_channels(Identity::OnBehalfOf(&user, &["Channel.ReadBasic.All"]), team_id, &ODataQuery::new()).await?;
+/// ```
+///
+#[derive(Clone, Copy, Default)]
+pub enum Identity<'a> {
+	/// The app registration with its application permissions.
+	#[default]
+	App,
+
+	/// A signed-in user whose token was issued for Graph, used as is.
+	User(&'a User),
+
+	/// A signed-in user whose token was issued to this app, exchanged for a Graph token with the given scopes.
+	OnBehalfOf(&'a User, &'a [&'a str]),
+}
+
+impl MSGraph {
+	/// The bearer token to send for `identity`.
+	///
+	/// # Errors
+	/// Returns `GraphError::Api` if the app token needs a refresh or the on-behalf-of exchange is rejected.
+	pub async fn bearer_token(&self, identity: Identity<'_>) -> Result<String, GraphError> {
+		match identity {
+			Identity::App => self.access_token().await,
+			Identity::User(user) => Ok(user.token.ms_token.access_token.clone()),
+			Identity::OnBehalfOf(user, scopes) => Ok(self.on_behalf_of(user, scopes).await?.access_token),
+		}
+	}
+}
@@ -1,31 +1,75 @@
 #![warn(clippy::pedantic, clippy::nursery, clippy::all)]
 #![allow(clippy::multiple_crate_versions, clippy::module_name_repetitions)]
 
-use std::collections::HashMap;
-
-use azure_security_keyvault::KeyvaultClient;
+pub use batch::*;
+pub use cache::*;
+pub use cassette::*;
+pub use certificate::*;
 pub use channel::*;
+pub use collection::*;
+pub use config::*;
+pub use credential::*;
 pub use drive::*;
 use eggersmann_app_server_auth::MSAccessToken;
 pub use eggersmann_app_server_auth::User;
+pub use error::*;
+#[cfg(feature = "rocket")]
+pub use fairing::*;
 pub use group::*;
+pub use identity::*;
 pub use me::*;
 pub use plan::*;
-use rocket::form::Form;
+pub use query::*;
+pub use registry::*;
+use reqwest::{
+	header::{CONTENT_TYPE, IF_MATCH},
+	StatusCode,
+};
+#[cfg(feature = "rocket")]
+pub use responder::*;
+pub use retry::*;
 use serde_json::json;
 pub use site::*;
+pub use task::*;
 pub use team::*;
+use token::TokenCache;
 
+mod batch;
+mod cache;
+mod cassette;
+mod certificate;
 mod channel;
+mod collection;
+mod config;
+mod credential;
 mod drive;
+mod error;
+#[cfg(feature = "rocket")]
+mod fairing;
 mod group;
+mod identity;
 mod me;
 mod plan;
+mod query;
+mod registry;
+mod request;
+#[cfg(feature = "rocket")]
+mod responder;
+mod retry;
+#[cfg(feature = "routes")]
+pub mod routes;
 mod site;
+mod task;
 mod team;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+mod token;
 
 pub struct MSGraph {
-	pub token: MSAccessToken,
+	config: MSGraphConfig,
+	client: reqwest::Client,
+	credentials: AppCredentials,
+	tokens: TokenCache,
 }
 
 impl MSGraph {
@@ -36,163 +80,150 @@ impl MSGraph {
 	/// `tenant_id_key`: The name of the secret that contains the tenant ID. (e.g. "ms-auth-tenant-id")
 	///
 	/// # Errors
-	/// todo
-	pub async fn new(key_vault_name: &str, client_id_key: &str, client_secret_key: &str, tenant_id_key: &str) -> Result<Self, String> {
-		//dbg!("MSGraph::new(key_vault_name: {}, client_id_key: {}, client_secret_key: {}, tenant_id_key: {})", key_vault_name, client_id_key, client_secret_key, tenant_id_key);
-		let azure_credentials = azure_identity::create_credential().map_err(|e| e.to_string())?;
-		//dbg!("MSGraph::new: 1");
-		let azure_key_vault_client = KeyvaultClient::new(&format!("https://{key_vault_name}.vault.azure.net"), azure_credentials).map_err(|e| e.to_string())?.secret_client();
-
-		//dbg!("MSGraph::new: 2");
-		// Get the secrets from the Azure Key Vault.
-		let client_id = match azure_key_vault_client.get(client_id_key).await {
-			Ok(client_id) => client_id.value,
-			Err(e) => return Err(e.to_string()),
-		};
-		//dbg!("MSGraph::new: 3");
-		let client_secret = match azure_key_vault_client.get(client_secret_key).await {
-			Ok(client_secret) => client_secret.value,
-			Err(e) => return Err(e.to_string()),
-		};
-
-		//dbg!("MSGraph::new: 4");
-		let tenant_id = match azure_key_vault_client.get(tenant_id_key).await {
-			Ok(tenant_id) => tenant_id.value,
-			Err(e) => return Err(e.to_string()),
-		};
-
-		//dbg!("MSGraph::new: 5");
-		let client = reqwest::Client::new();
-		let scopes = ["https://graph.microsoft.com/.default".to_string(), "files.read".to_string(), "files.readwrite".to_string(), "files.read.all".to_string(), "files.readwrite.all".to_string(), "offline_access".to_string()].join(" ").to_string();
-		let mut params = HashMap::new();
-		params.insert("client_id", client_id.as_str());
-		params.insert("client_secret", client_secret.as_str());
-		params.insert("grant_type", "client_credentials");
-		params.insert("scope", &scopes);
-
-		//dbg!("MSGraph::new: 6");
-		let res = client.post(format!("https://login.microsoftonline.com/{tenant_id}/oauth2/v2.0/token")).form(&params).send().await;
-		match res {
-			Ok(res) => {
-				let token = res.json::<MSAccessToken>().await;
-				match token {
-					Ok(token) => Ok(Self { token }),
-					Err(err) => Err(err.to_string()),
-				}
-			}
-			Err(err) => Err(err.to_string()),
+	/// Returns `GraphError::KeyVault` if a secret cannot be read and `GraphError::Api` if the token request is rejected.
+	pub async fn new(key_vault_name: &str, client_id_key: &str, client_secret_key: &str, tenant_id_key: &str) -> Result<Self, GraphError> {
+		Self::from_provider(&KeyVaultCredentials::new(key_vault_name, client_id_key, client_secret_key, tenant_id_key)).await
+	}
+
+	/// Create a new `MSGraph` instance with the credentials from `provider`, e.g. `EnvCredentials` for local development.
+	///
+	/// # Errors
+	/// Returns the provider's error if the credentials cannot be read and `GraphError::Api` if the token request is rejected.
+	pub async fn from_provider(provider: &impl CredentialProvider) -> Result<Self, GraphError> {
+		Self::with_config(MSGraphConfig::default(), provider).await
+	}
+
+	/// Create a new `MSGraph` instance against the endpoints in `config`, e.g. a national cloud or a local test server.
+	///
+	/// # Errors
+	/// Returns the provider's error if the credentials cannot be read and `GraphError::Api` if the token request is rejected.
+	pub async fn with_config(config: MSGraphConfig, provider: &impl CredentialProvider) -> Result<Self, GraphError> {
+		Self::with_client(reqwest::Client::new(), config, provider).await
+	}
+
+	/// Create a new `MSGraph` instance that sends every request through `client`, e.g. one built with custom timeouts, a proxy or a user agent.
+	///
+	/// # Errors
+	/// Returns the provider's error if the credentials cannot be read and `GraphError::Api` if the token request is rejected.
+	pub async fn with_client(client: reqwest::Client, config: MSGraphConfig, provider: &impl CredentialProvider) -> Result<Self, GraphError> {
+		let credentials = provider.credentials().await?;
+		if let Some(cassette) = &config.cassette {
+			cassette.add_scrub(&credentials.tenant_id, CASSETTE_TENANT_ID);
+			cassette.add_scrub(&credentials.client_id, CASSETTE_CLIENT_ID);
 		}
+		let tokens = TokenCache::new(config.token_store.clone());
+		let graph = Self { config, client, credentials, tokens };
+		graph.token().await?;
+		Ok(graph)
+	}
+
+	/// The tenant this instance authenticates against.
+	#[must_use]
+	pub fn tenant_id(&self) -> &str {
+		&self.credentials.tenant_id
+	}
+
+	/// The endpoints this instance talks to.
+	#[must_use]
+	pub const fn config(&self) -> &MSGraphConfig {
+		&self.config
+	}
+
+	/// The app access token from the token cache, refreshed first if it is about to expire.
+	/// Concurrent callers wait for a single refresh instead of each requesting a token.
+	///
+	/// # Errors
+	/// Returns `GraphError::Api` if a needed refresh is rejected by the token endpoint.
+	pub async fn access_token(&self) -> Result<String, GraphError> {
+		Ok(self.token().await?.access_token)
+	}
+
+	/// The full app token response, refreshed first if it is about to expire.
+	///
+	/// # Errors
+	/// Returns `GraphError::Api` if a needed refresh is rejected by the token endpoint.
+	pub async fn token(&self) -> Result<MSAccessToken, GraphError> {
+		let key = token::app_key(&self.config, &self.credentials);
+		let token = self.tokens.get(&key, || token::request_app_token(&self.client, &self.config, &self.credentials)).await?;
+		Ok(token.token)
+	}
+
+	/// Request a new app token now, regardless of the current token's expiry.
+	///
+	/// # Errors
+	/// Returns `GraphError::Api` if the token endpoint rejects the request; the previous token is kept.
+	pub async fn force_refresh(&self) -> Result<(), GraphError> {
+		let key = token::app_key(&self.config, &self.credentials);
+		self.tokens.refresh(&key, || token::request_app_token(&self.client, &self.config, &self.credentials)).await?;
+		Ok(())
+	}
+
+	/// Exchange the signed-in `user`'s token for a Graph token carrying `scopes`, so calls made with it run as that user and show up under their name in audit logs.
+	/// `scopes` are Graph permissions such as "Group.ReadWrite.All"; an empty slice requests every delegated permission consented for the app.
+	/// The user's token must have been issued to this app registration, not to Graph.
+	/// Exchanged tokens are cached per user and scope set until shortly before they expire.
+	///
+	/// # Errors
+	/// Returns `GraphError::Api` if the token endpoint rejects the exchange, e.g. with `invalid_grant` when consent is missing.
+	pub async fn on_behalf_of(&self, user: &User, scopes: &[&str]) -> Result<MSAccessToken, GraphError> {
+		let assertion = &user.token.ms_token.access_token;
+		let key = token::on_behalf_of_key(&self.config, &self.credentials, assertion, scopes);
+		let token = self.tokens.get(&key, || token::request_on_behalf_of_token(&self.client, &self.config, &self.credentials, assertion, scopes)).await?;
+		Ok(token.token)
 	}
 
 	/// Get the current user.
 	/// # Errors
-	/// todo
-	pub async fn me(&self, user: User) -> Result<Me, String> {
-		let client = reqwest::Client::new();
-		let res = client.get("https://graph.microsoft.com/v1.0/me").bearer_auth(&user.token.ms_token.access_token).send().await;
-
-		match res {
-			Ok(res) => {
-				let json = res.json::<Me>().await;
-				match json {
-					Ok(json) => Ok(json),
-					Err(err) => Err(err.to_string()),
-				}
-			}
-			Err(err) => Err(err.to_string()),
-		}
+	/// Returns `GraphError::Api` if Graph rejects the request and `GraphError::Deserialize` if the response is not a user.
+	pub async fn me(&self, user: User) -> Result<Me, GraphError> {
+		let res = self.send(self.client.get(self.config.url("/me")).bearer_auth(&user.token.ms_token.access_token)).await?;
+		request::json(res).await
 	}
 
 	/// Get the photo of the current user.
 	/// # Errors
-	/// todo
-	pub async fn me_photo(&self, user: User) -> Result<Vec<u8>, String> {
-		let client = reqwest::Client::new();
-		let res = client.get("https://graph.microsoft.com/v1.0/me/photo/$value").bearer_auth(&user.token.ms_token.access_token).send().await;
-		match res {
-			Ok(res) => {
-				let bytes = res.bytes().await;
-				match bytes {
-					Ok(bytes) => Ok(bytes.to_vec()),
-					Err(err) => Err(err.to_string()),
-				}
-			}
-			Err(err) => Err(err.to_string()),
+	/// Returns `GraphError::Api` if Graph rejects the request, e.g. 404 when the user has no photo.
+	pub async fn me_photo(&self, user: User) -> Result<Photo, GraphError> {
+		let res = self.send(self.client.get(self.config.url("/me/photo/$value")).bearer_auth(&user.token.ms_token.access_token)).await?;
+		let url = res.url().to_string();
+		let content_type = res.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok()).map(ToOwned::to_owned);
+		match res.bytes().await {
+			Ok(bytes) => Ok(Photo { content_type, bytes: bytes.to_vec() }),
+			Err(source) => Err(GraphError::Request { url, source }),
 		}
 	}
 
-	/// Create a new shared channel in a team.
+	/// Create a new shared channel in a team as `identity`; tenants that block app-only shared-channel creation need `Identity::User` or `Identity::OnBehalfOf`.
 	/// # Errors
-	/// todo
-	pub async fn automation_teams_create_shared_channel(&self, data: Form<CreateSharedChannelForm>) -> Result<(Team, Channel, Channel), String> {
-		//let automation = MSGraphAutomation::new().await;
-		let client = reqwest::Client::new();
+	/// Returns the first `GraphError` hit while creating the channel, adding its owner or setting up the plan.
+	pub async fn automation_teams_create_shared_channel(&self, identity: Identity<'_>, data: &CreateSharedChannelForm) -> Result<(Team, Channel, Channel), GraphError> {
+		let members = vec![ChannelMember { odata_type: "#microsoft.graph.aadUserConversationMember".to_string(), user_odata_bind: self.config.url(&format!("/users('{}')", data.owner_id)), roles: vec!["owner".to_string()] }];
 
-		let members = vec![ChannelMember {
-			odata_type: "#microsoft.graph.aadUserConversationMember".to_string(),
-			user_odata_bind: "https://graph.microsoft.com/v1.0/users('".to_string() + &data.owner_id + "')",
-			roles: vec!["owner".to_string()],
-		}];
-
-		let team = match self.automation_team_by_name(data.team_name.clone()).await {
-			Ok(team) => team,
-			Err(err) => return Err(format!(" Error getting team {}: {}", data.team_name.clone(), err)),
-		};
+		let team = self.automation_team_by_name(identity, data.team_name.clone()).await?;
 
 		let body = json!(CreateChannelBody { display_name: data.channel_display_name.clone(), description: data.channel_description.clone(), membership_type: "shared".to_string(), members });
-		let uri = format!("https://graph.microsoft.com/v1.0/teams/{}/channels", team.id.clone());
-		let res = client.post(uri).json(&body).bearer_auth(&self.token.access_token).send().await;
-
-		match res {
-			Ok(_) => {
-				let team = match self.automation_team_by_name(data.team_name.clone()).await {
-					Ok(team) => team,
-					Err(err) => return Err(format!("Error getting team: {err}")),
-				};
-				let channel = match self.automation_channel_by_name(team.id.clone(), data.channel_display_name.clone()).await {
-					Ok(channel) => channel,
-					Err(err) => return Err(format!("Error getting newly created channel: {err}")),
-				};
-				let general = match self.automation_channel_by_name(team.id.clone(), "General".to_owned()).await {
-					Ok(channel) => channel,
-					Err(err) => return Err(format!("Error getting general channel: {err}")),
-				};
-
-				// add member_id as owner to channel
-				let res = self.automation_add_channel_owner(team.clone(), channel.clone(), data.member_id.clone()).await;
-				match res {
-					Ok(()) => (),
-					Err(err) => return Err(format!("Error adding owner to channel: {err}")),
-				}
+		let uri = self.config.url(&format!("/teams/{}/channels", team.id));
+		self.send(self.client.post(uri).json(&body).bearer_auth(self.bearer_token(identity).await?)).await?;
 
-				if let Some(plan) = &data.plan {
-					let plan = plan.to_create_plan();
-					let created_plan = self.automation_create_plan(plan.plan_name.clone(), team.display_name.clone().ok_or("Team display name not found")?.clone()).await;
-					let created_plan = match created_plan {
-						Ok(created_plan) => created_plan,
-						Err(err) => return Err(format!("Error creating plan: {err}")),
-					};
-
-					let spec = plan.plan_template.to_spec();
-
-					for (bucket_name, _) in spec.buckets {
-						let res = self.automation_add_bucket_to_plan(created_plan.title.clone().unwrap_or_else(|| plan.plan_name.clone()).clone(), team.display_name.clone().ok_or("Team display name not found")?.clone(), bucket_name.clone()).await;
-						match res {
-							Ok(_) => (),
-							Err(err) => return Err(format!("Error adding bucket to plan: {err}")),
-						}
-					}
-
-					let res = self.automation_add_plan_tab_to_teams_channel(&format!("{} Tasks", &channel.display_name.clone().ok_or("Channel display name not found")?), team.clone(), channel.clone(), created_plan).await;
-					match res {
-						Ok(_) => (),
-						Err(err) => return Err(format!("Error adding plan tab to channel: {err}")),
-					}
-				}
-				Ok((team, channel, general))
-			}
-			Err(err) => Err(format!("Error creating channel: {err}")),
+		let team = self.automation_team_by_name(identity, data.team_name.clone()).await?;
+		let channel = self.automation_channel_by_name(identity, team.id.clone(), data.channel_display_name.clone()).await?;
+		let general = self.automation_channel_by_name(identity, team.id.clone(), "General".to_owned()).await?;
+
+		// add member_id as owner to channel
+		self.automation_add_channel_owner(identity, team.clone(), channel.clone(), data.member_id.clone()).await?;
+
+		if let Some(plan) = &data.plan {
+			let plan = plan.to_create_plan();
+			let team_name = team.display_name.clone().ok_or(GraphError::MissingField("Team display name"))?;
+			let created_plan = self.automation_create_plan(identity, plan.plan_name.clone(), team_name.clone()).await?;
+
+			let spec = plan.plan_template.to_spec();
+			self.automation_add_buckets_to_plan(identity, created_plan.id.clone(), spec.buckets.into_keys().collect()).await?;
+
+			let channel_name = channel.display_name.clone().ok_or(GraphError::MissingField("Channel display name"))?;
+			self.automation_add_plan_tab_to_teams_channel(identity, &format!("{channel_name} Tasks"), team.clone(), channel.clone(), created_plan).await?;
 		}
+		Ok((team, channel, general))
 	}
 
 	/// Add a owner to a channel.
@@ -208,323 +239,331 @@ impl MSGraph {
 	///
 	///
 	/// # Errors
-	/// todo
-	pub async fn automation_add_channel_owner(&self, team: Team, channel: Channel, owner_id: String) -> Result<(), String> {
-		let client = reqwest::Client::new();
-
-		let body = json!(ChannelMember {
-			odata_type: "#microsoft.graph.aadUserConversationMember".to_string(),
-			user_odata_bind: "https://graph.microsoft.com/v1.0/users('".to_string() + &owner_id + "')",
-			roles: vec!["owner".to_string()],
-		});
+	/// Returns `GraphError::Api` if Graph rejects the new member.
+	pub async fn automation_add_channel_owner(&self, identity: Identity<'_>, team: Team, channel: Channel, owner_id: String) -> Result<(), GraphError> {
+		let body = json!(ChannelMember { odata_type: "#microsoft.graph.aadUserConversationMember".to_string(), user_odata_bind: self.config.url(&format!("/users('{owner_id}')")), roles: vec!["owner".to_string()] });
 
-		let uri = format!("https://graph.microsoft.com/v1.0/teams/{}/channels/{}/members", team.id.clone(), channel.id.clone().ok_or("Channel ID not found")?);
-		let res = client.post(uri).json(&body).bearer_auth(&self.token.access_token).send().await;
+		let uri = self.config.url(&format!("/teams/{}/channels/{}/members", team.id, channel.id.clone().ok_or(GraphError::MissingField("Channel ID"))?));
+		self.send(self.client.post(uri).json(&body).bearer_auth(self.bearer_token(identity).await?)).await?;
+		Ok(())
+	}
 
-		match res {
-			Ok(_) => Ok(()),
-			Err(err) => Err(format!("Error adding owner: {err}")),
-		}
+	/// Get all channels of a team
+	/// # Errors
+	/// Returns `GraphError::Api` if Graph rejects the request.
+	pub async fn automation_channels(&self, identity: Identity<'_>, team_id: String, query: &ODataQuery) -> Result<Vec<Channel>, GraphError> {
+		self.collect_all(identity, &self.config.url(&format!("/teams/{team_id}/channels")), query).await
 	}
 
 	/// Get a channel by name
 	/// # Errors
-	/// todo
-	pub async fn automation_channel_by_name(&self, team_id: String, channel_name: String) -> Result<Channel, String> {
-		let client = reqwest::Client::new();
-		let res = client.get(format!("https://graph.microsoft.com/v1.0/teams/{team_id}/channels")).bearer_auth(&self.token.access_token).send().await;
-		match res {
-			Ok(res) => {
-				let body = match res.text().await {
-					Ok(body) => body,
-					Err(err) => return Err(format!("Error Decoding Body: {err}")),
-				};
-				let json = serde_json::from_str::<ChannelCollection>(&body);
-				match json {
-					Ok(json) => {
-						let channel = json.value.iter().find(|channel| channel.display_name == Some(channel_name.clone()));
-						channel.map_or_else(|| Err("No matching channel found.".to_string()), |channel| Ok(channel.clone()))
-					}
-					Err(err) => Err(format!("Error Deserializing Channels JSON: {err}")),
-				}
-			}
-			Err(err) => Err(format!("Error getting Channels: {err}")),
-		}
+	/// Returns `GraphError::NotFound` if the team has no channel with that name and `GraphError::Ambiguous` if it has several.
+	pub async fn automation_channel_by_name(&self, identity: Identity<'_>, team_id: String, channel_name: String) -> Result<Channel, GraphError> {
+		let query = ODataQuery::new().filter(&format!("displayName eq {}", ODataQuery::literal(&channel_name)));
+		let channels = self.automation_channels(identity, team_id, &query).await?;
+		request::single(channels, format!("channel {channel_name}"))
 	}
 
 	/// Get all groups that have the Team resourceProvisioningOption
+	/// `query` may add its own `$filter`; it is combined with the Team filter.
 	/// # Errors
-	/// todo
-	pub async fn automation_groups_with_teams(&self) -> Result<Vec<Group>, String> {
-		let client = reqwest::Client::new();
-		let res = client.get("https://graph.microsoft.com/beta/groups?$filter=resourceProvisioningOptions/Any(x:x+eq+'Team')").bearer_auth(&self.token.access_token).send().await;
-		match res {
-			Ok(res) => {
-				let group_collection = res.json::<GroupCollection>().await;
-				match group_collection {
-					Ok(group_collection) => Ok(group_collection.value),
-					Err(err) => Err(err.to_string()),
-				}
-			}
-			Err(err) => Err(err.to_string()),
-		}
+	/// Returns `GraphError::Api` if Graph rejects the request.
+	pub async fn automation_groups_with_teams(&self, identity: Identity<'_>, query: &ODataQuery) -> Result<Vec<Group>, GraphError> {
+		let query = query.clone().and_filter("resourceProvisioningOptions/Any(x:x eq 'Team')");
+		self.collect_all(identity, &self.config.beta_url("/groups"), &query).await
 	}
 
 	/// Get a team by group id
 	/// # Errors
-	/// todo
-	pub async fn automation_team_by_group_id(&self, group_id: String) -> Result<Team, String> {
-		let client = reqwest::Client::new();
-		let res = client.get(format!("https://graph.microsoft.com/v1.0/groups/{group_id}/team")).bearer_auth(&self.token.access_token).send().await;
-		match res {
-			Ok(res) => {
-				let team = res.json::<Team>().await;
-				match team {
-					Ok(team) => Ok(team),
-					Err(err) => Err(err.to_string()),
-				}
-			}
-			Err(err) => Err(err.to_string()),
-		}
+	/// Returns `GraphError::Api` if Graph rejects the request, e.g. 404 when the group has no team.
+	pub async fn automation_team_by_group_id(&self, identity: Identity<'_>, group_id: String) -> Result<Team, GraphError> {
+		let res = self.send(self.client.get(self.config.url(&format!("/groups/{group_id}/team"))).bearer_auth(self.bearer_token(identity).await?)).await?;
+		request::json(res).await
 	}
 
 	/// Get team by name
 	/// # Errors
-	/// todo
-	pub async fn automation_team_by_name(&self, team_name: String) -> Result<Team, String> {
-		let groups_with_teams = match self.automation_groups_with_teams().await {
-			Ok(groups_with_teams) => groups_with_teams,
-			Err(err) => return Err(format!("Error getting groups with teams: {err}")),
-		};
-
-		let Some(group) = groups_with_teams.iter().find(|group| group.display_name == Some(team_name.clone())) else { return Err(format!("Could not find group with name {}", team_name.clone())) };
+	/// Returns `GraphError::NotFound` if no team-enabled group has that name and `GraphError::Ambiguous` if several do.
+	pub async fn automation_team_by_name(&self, identity: Identity<'_>, team_name: String) -> Result<Team, GraphError> {
+		let query = ODataQuery::new().filter(&format!("displayName eq {}", ODataQuery::literal(&team_name))).select(&["id", "displayName"]);
+		let groups_with_teams = self.automation_groups_with_teams(identity, &query).await?;
+		let group = request::single(groups_with_teams, format!("group {team_name}"))?;
 
-		let team = match self.automation_team_by_group_id(group.id.clone()).await {
-			Ok(team) => team,
-			Err(err) => return Err(format!("Error getting team by id {}: {}", group.id.clone(), err)),
-		};
-
-		Ok(team)
+		self.automation_team_by_group_id(identity, group.id).await
 	}
 
 	/// # Errors
-	/// todo
-	pub async fn sites(&self) -> Result<String, String> {
-		let client = reqwest::Client::new();
-		let res = client.get("https://graph.microsoft.com/beta/sites").bearer_auth(&self.token.access_token).send().await;
-		match res {
-			Ok(res) => {
-				let json = res.json::<serde_json::Value>().await;
-				match json {
-					Ok(json) => Ok(json.to_string()),
-					Err(err) => Err(err.to_string()),
-				}
-			}
-			Err(err) => Err(err.to_string()),
-		}
+	/// Returns `GraphError::Api` if Graph rejects the request.
+	pub async fn sites(&self, identity: Identity<'_>, query: &ODataQuery) -> Result<Vec<Site>, GraphError> {
+		self.collect_all(identity, &self.config.beta_url("/sites"), query).await
 	}
 
+	/// Sites do not support `$filter` on `displayName`, so this asks the site search endpoint and keeps the exact matches.
 	/// # Errors
-	/// todo
-	pub async fn get_site_by_name(&self, site_name: String) -> Result<Site, String> {
-		let sites = self.sites().await;
-		match sites {
-			Ok(sites) => {
-				let json = serde_json::from_str::<SiteCollection>(&sites);
-				match json {
-					Ok(json) => {
-						let site = json.value.iter().find(|site| site.display_name == Some(site_name.clone()));
-						site.map_or_else(|| Err("No matching site found.".to_string()), |site| Ok(site.clone()))
-					}
-					Err(err) => Err(format!("Error Deserializing Sites JSON: {err}")),
-				}
-			}
-			Err(err) => Err(err),
-		}
+	/// Returns `GraphError::NotFound` if no site has that display name and `GraphError::Ambiguous` if several do.
+	pub async fn get_site_by_name(&self, identity: Identity<'_>, site_name: String) -> Result<Site, GraphError> {
+		let url = self.config.beta_url(&format!("/sites?search={}", request::encode(&site_name)));
+		let sites = self.collect_all::<Site>(identity, &url, &ODataQuery::new()).await?;
+		let sites = sites.into_iter().filter(|site| site.display_name.as_deref() == Some(site_name.as_str())).collect();
+		request::single(sites, format!("site {site_name}"))
 	}
 
 	/// # Errors
-	/// todo
-	pub async fn site_pages(&self, site_id: String) -> Result<String, String> {
-		let client = reqwest::Client::new();
-		let res = client.get(format!("https://graph.microsoft.com/beta/sites/{site_id}/pages")).bearer_auth(&self.token.access_token).send().await;
-		match res {
-			Ok(res) => {
-				let json = res.json::<serde_json::Value>().await;
-				match json {
-					Ok(json) => Ok(json.to_string()),
-					Err(err) => Err(err.to_string()),
-				}
-			}
-			Err(err) => Err(err.to_string()),
-		}
+	/// Returns `GraphError::Api` if Graph rejects the request.
+	pub async fn site_pages(&self, identity: Identity<'_>, site_id: String, query: &ODataQuery) -> Result<Vec<serde_json::Value>, GraphError> {
+		self.collect_all(identity, &self.config.beta_url(&format!("/sites/{site_id}/pages")), query).await
 	}
 
 	/// # Errors
-	/// todo
-	pub async fn site_drives(&self, site_id: String) -> Result<DriveCollection, String> {
-		let client = reqwest::Client::new();
-		let res = client.get(format!("https://graph.microsoft.com/beta/sites/{site_id}/drives")).bearer_auth(&self.token.access_token).send().await;
-		match res {
-			Ok(res) => match res.json::<DriveCollection>().await {
-				Ok(collection) => Ok(collection),
-				Err(err) => Err(err.to_string()),
-			},
-			Err(err) => Err(err.to_string()),
-		}
+	/// Returns `GraphError::Api` if Graph rejects the request.
+	pub async fn site_drives(&self, identity: Identity<'_>, site_id: String, query: &ODataQuery) -> Result<Vec<Drive>, GraphError> {
+		self.collect_all(identity, &self.config.beta_url(&format!("/sites/{site_id}/drives")), query).await
 	}
 
+	/// Drives do not support `$filter`, so this lists a site's drives and matches the name locally; sites hold only a handful of drives.
 	/// # Errors
-	/// todo
-	pub async fn get_drive_by_name(&self, site_id: String, drive_name: String) -> Result<Drive, String> {
-		let drives = self.site_drives(site_id).await;
-		match drives {
-			Ok(drives) => {
-				let drive = drives.value.iter().find(|drive| drive.name == Some(drive_name.clone()));
-				drive.map_or_else(|| Err("No matching drive found.".to_string()), |drive| Ok(drive.clone()))
-			}
-			Err(err) => Err(err),
-		}
+	/// Returns `GraphError::NotFound` if the site has no drive with that name and `GraphError::Ambiguous` if it has several.
+	pub async fn get_drive_by_name(&self, identity: Identity<'_>, site_id: String, drive_name: String) -> Result<Drive, GraphError> {
+		let drives = self.site_drives(identity, site_id, &ODataQuery::new()).await?;
+		let drives = drives.into_iter().filter(|drive| drive.name.as_deref() == Some(drive_name.as_str())).collect();
+		request::single(drives, format!("drive {drive_name}"))
 	}
 
 	/// # Errors
-	/// todo
-	pub async fn put_item_in_site_drive(&self, site_name: &str, drive_name: &str, file_name: &str, item: Vec<u8>) -> Result<String, String> {
-		let site = match self.get_site_by_name(site_name.to_string()).await {
-			Ok(site) => site,
-			Err(err) => return Err(err),
-		};
-		let drive = match self.get_drive_by_name(site.id.clone(), drive_name.to_owned()).await {
-			Ok(drive) => drive,
-			Err(err) => return Err(err),
-		};
-
-		let client = reqwest::Client::new();
-		let res = client.put(format!("https://graph.microsoft.com/beta/drives/{}/items/{}:/{}:/content", drive.id, site.id, file_name)).bearer_auth(&self.token.access_token).body(item).send().await;
-		match res {
-			Ok(res) => {
-				let json = res.json::<serde_json::Value>().await;
-				match json {
-					Ok(json) => Ok(json.to_string()),
-					Err(err) => Err(err.to_string()),
-				}
-			}
-			Err(err) => Err(err.to_string()),
-		}
+	/// Returns `GraphError::NotFound` if the site or drive does not exist and `GraphError::Api` if Graph rejects the upload.
+	pub async fn put_item_in_site_drive(&self, identity: Identity<'_>, site_name: &str, drive_name: &str, file_name: &str, item: Vec<u8>) -> Result<String, GraphError> {
+		let site = self.get_site_by_name(identity, site_name.to_string()).await?;
+		let drive = self.get_drive_by_name(identity, site.id.clone(), drive_name.to_owned()).await?;
+
+		let res = self.send(self.client.put(self.config.beta_url(&format!("/drives/{}/root:/{}:/content", drive.id, request::encode(file_name)))).bearer_auth(self.bearer_token(identity).await?).body(item)).await?;
+		let json = request::json::<serde_json::Value>(res).await?;
+		Ok(json.to_string())
 	}
 
 	/// # Errors
-	/// todo
-	pub async fn automation_create_plan(&self, plan_name: String, team_name: String) -> Result<Plan, String> {
-		let team = match self.automation_team_by_name(team_name).await {
-			Ok(team) => team,
-			Err(err) => return Err(err),
-		};
+	/// Returns `GraphError::NotFound` if the team does not exist and `GraphError::Api` if Graph rejects the plan.
+	pub async fn automation_create_plan(&self, identity: Identity<'_>, plan_name: String, team_name: String) -> Result<Plan, GraphError> {
+		let team = self.automation_team_by_name(identity, team_name).await?;
 
-		let client = reqwest::Client::new();
 		let body = json!({
 			"container": {
-				"url": format!("https://graph.microsoft.com/beta/groups/{}", team.id),
+				"url": self.config.beta_url(&format!("/groups/{}", team.id)),
 			},
 			"title": plan_name,
 		});
 
-		let res = client.post("https://graph.microsoft.com/beta/planner/plans").json(&body).bearer_auth(&self.token.access_token);
+		let res = self.send(self.client.post(self.config.beta_url("/planner/plans")).json(&body).bearer_auth(self.bearer_token(identity).await?)).await?;
+		request::json(res).await
+	}
 
-		match res.send().await {
-			Ok(res) => {
-				let json = res.json::<serde_json::Value>().await.map_err(|e| e.to_string())?;
-				let plan: Plan = match serde_json::from_value(json) {
-					Ok(plan) => plan,
-					Err(err) => return Err(err.to_string()),
-				};
-				Ok(plan)
-			}
-			Err(err) => Err(err.to_string()),
-		}
+	/// Get all plans owned by a group
+	/// # Errors
+	/// Returns `GraphError::Api` if Graph rejects the request.
+	pub async fn automation_plans(&self, identity: Identity<'_>, group_id: String, query: &ODataQuery) -> Result<Vec<Plan>, GraphError> {
+		self.collect_all(identity, &self.config.beta_url(&format!("/groups/{group_id}/planner/plans")), query).await
 	}
 
+	/// Planner does not support `$filter` on plans, so a group's plans are listed and matched by title locally.
 	/// # Errors
-	/// todo
-	pub async fn automation_get_plan_by_name(&self, plan_name: String, team_name: String) -> Result<Plan, String> {
-		let team = match self.automation_team_by_name(team_name).await {
-			Ok(team) => team,
-			Err(err) => return Err(err),
-		};
-
-		let client = reqwest::Client::new();
-		let res = client.get(format!("https://graph.microsoft.com/beta/groups/{}/planner/plans", team.id)).bearer_auth(&self.token.access_token).send().await.map_err(|e| e.to_string())?;
-		match res.json::<PlanCollection>().await {
-			Ok(plans) => {
-				let Some(plans) = plans.value else { return Err("No plans found.".to_string()) };
-				let plan = plans.iter().find(|plan| plan.title == Some(plan_name.clone()));
-				plan.map_or_else(|| Err("No matching plan found.".to_string()), |plan| Ok(plan.clone()))
-			}
-			Err(err) => Err(err.to_string()),
-		}
+	/// Returns `GraphError::NotFound` if the team or plan does not exist and `GraphError::Ambiguous` if several plans share the title.
+	pub async fn automation_get_plan_by_name(&self, identity: Identity<'_>, plan_name: String, team_name: String) -> Result<Plan, GraphError> {
+		let team = self.automation_team_by_name(identity, team_name).await?;
+
+		let plans = self.automation_plans(identity, team.id, &ODataQuery::new()).await?;
+		let plans = plans.into_iter().filter(|plan| plan.title.as_deref() == Some(plan_name.as_str())).collect();
+		request::single(plans, format!("plan {plan_name}"))
 	}
 
 	/// # Errors
-	/// todo
-	pub async fn automation_add_bucket_to_plan(&self, plan_name: String, team_name: String, bucket_name: String) -> Result<Bucket, String> {
-		let plan = match self.automation_get_plan_by_name(plan_name, team_name).await {
-			Ok(plan) => plan,
-			Err(err) => return Err(err),
-		};
+	/// Returns `GraphError::NotFound` if the plan does not exist and `GraphError::Api` if Graph rejects the bucket.
+	pub async fn automation_add_bucket_to_plan(&self, identity: Identity<'_>, plan_name: String, team_name: String, bucket_name: String) -> Result<Bucket, GraphError> {
+		let plan = self.automation_get_plan_by_name(identity, plan_name, team_name).await?;
 
-		let client = reqwest::Client::new();
 		let body = json!({
 				"name": bucket_name,
 				"planId": plan.id,
 				"orderHint": " !",
 		});
 
-		let res = client.post("https://graph.microsoft.com/beta/planner/buckets").json(&body).bearer_auth(&self.token.access_token);
+		let res = self.send(self.client.post(self.config.beta_url("/planner/buckets")).json(&body).bearer_auth(self.bearer_token(identity).await?)).await?;
+		request::json(res).await
+	}
 
-		match res.send().await {
-			Ok(res) => {
-				let json = res.json::<serde_json::Value>().await.map_err(|e| e.to_string())?;
-				let bucket: Bucket = match serde_json::from_value(json) {
-					Ok(bucket) => bucket,
-					Err(err) => return Err(err.to_string()),
-				};
-				Ok(bucket)
+	/// Add several buckets to a plan, sending up to 20 per `$batch` round-trip.
+	/// # Errors
+	/// Returns the `GraphError` of the first bucket Graph rejects.
+	pub async fn automation_add_buckets_to_plan(&self, identity: Identity<'_>, plan_id: String, bucket_names: Vec<String>) -> Result<Vec<Bucket>, GraphError> {
+		let mut buckets = Vec::with_capacity(bucket_names.len());
+		for chunk in bucket_names.chunks(MAX_BATCH_SIZE) {
+			let batch = chunk.iter().enumerate().fold(BatchRequest::beta(), |batch, (i, bucket_name)| {
+				batch.request(BatchRequestItem::post(
+					&i.to_string(),
+					"/planner/buckets",
+					json!({
+						"name": bucket_name,
+						"planId": plan_id,
+						"orderHint": " !",
+					}),
+				))
+			});
+			let responses = self.batch(identity, batch).await?;
+			for i in 0..chunk.len() {
+				buckets.push(responses.json(&i.to_string())?);
 			}
-			Err(err) => Err(err.to_string()),
 		}
+		Ok(buckets)
+	}
+
+	/// Get all buckets of a plan
+	/// # Errors
+	/// Returns `GraphError::Api` if Graph rejects the request.
+	pub async fn automation_buckets(&self, identity: Identity<'_>, plan_id: String, query: &ODataQuery) -> Result<Vec<Bucket>, GraphError> {
+		self.collect_all(identity, &self.config.beta_url(&format!("/planner/plans/{plan_id}/buckets")), query).await
+	}
+
+	/// # Errors
+	/// Returns `GraphError::Api` if Graph rejects the task, e.g. because the bucket is not in the plan.
+	pub async fn automation_create_task(&self, identity: Identity<'_>, task: &CreatePlannerTask) -> Result<PlannerTask, GraphError> {
+		let mut body = json!(task);
+		if !task.assignees.is_empty() {
+			let user_ids = self.automation_user_ids(identity, &task.assignees).await?;
+			body["assignments"] = serde_json::Value::Object(assignments_after(None, user_ids));
+		}
+		let res = self.send(self.client.post(self.config.beta_url("/planner/tasks")).json(&body).bearer_auth(self.bearer_token(identity).await?)).await?;
+		request::json(res).await
+	}
+
+	/// # Errors
+	/// Returns `GraphError::Api` with 404 if the task does not exist.
+	pub async fn automation_get_task(&self, identity: Identity<'_>, task_id: &str) -> Result<PlannerTask, GraphError> {
+		let res = self.send(self.client.get(self.config.beta_url(&format!("/planner/tasks/{task_id}"))).bearer_auth(self.bearer_token(identity).await?)).await?;
+		request::json(res).await
+	}
+
+	/// Get all tasks of a plan
+	/// # Errors
+	/// Returns `GraphError::Api` if Graph rejects the request.
+	pub async fn automation_plan_tasks(&self, identity: Identity<'_>, plan_id: String, query: &ODataQuery) -> Result<Vec<PlannerTask>, GraphError> {
+		self.collect_all(identity, &self.config.beta_url(&format!("/planner/plans/{plan_id}/tasks")), query).await
 	}
 
+	/// Get all tasks of a bucket
 	/// # Errors
-	/// todo
-	pub async fn automation_add_plan_tab_to_teams_channel(&self, tab_name: &str, team: Team, channel: Channel, plan: Plan) -> Result<TeamsTab, String> {
-		let client = reqwest::Client::new();
+	/// Returns `GraphError::Api` if Graph rejects the request.
+	pub async fn automation_bucket_tasks(&self, identity: Identity<'_>, bucket_id: String, query: &ODataQuery) -> Result<Vec<PlannerTask>, GraphError> {
+		self.collect_all(identity, &self.config.beta_url(&format!("/planner/buckets/{bucket_id}/tasks")), query).await
+	}
+
+	/// Change `task` as it was last read; Planner rejects the update with 412 if the task changed since, so read it again and retry.
+	/// # Errors
+	/// Returns `GraphError::MissingField` if `task` has no `@odata.etag` and `GraphError::Api` with 412 if it is outdated.
+	pub async fn automation_update_task(&self, identity: Identity<'_>, task: &PlannerTask, update: &PlannerTaskUpdate) -> Result<PlannerTask, GraphError> {
+		let etag = task.odata_etag.as_deref().ok_or(GraphError::MissingField("Task ETag"))?;
+		let url = self.config.beta_url(&format!("/planner/tasks/{}", task.id));
+		let res = self.send(self.client.patch(url).header(IF_MATCH, etag).header("Prefer", "return=representation").json(update).bearer_auth(self.bearer_token(identity).await?)).await?;
+		request::json(res).await
+	}
 
-		// add app to team
+	/// Add assignees to `task` as it was last read, by user ID or user principal name, after its current assignees and in the order given; users already assigned keep their place.
+	/// # Errors
+	/// Returns `GraphError::Api` with 404 if a user principal name is unknown and with 412 if the task is outdated.
+	pub async fn automation_assign_task(&self, identity: Identity<'_>, task: &PlannerTask, users: &[&str]) -> Result<PlannerTask, GraphError> {
+		let assigned = task.assignee_ids();
+		let user_ids = self.automation_user_ids(identity, users).await?.into_iter().filter(|user_id| !assigned.contains(&user_id.as_str())).collect();
+		self.patch_task_assignments(identity, task, assignments_after(task.last_assignment_order_hint(), user_ids)).await
+	}
+
+	/// Remove assignees from `task` as it was last read, by user ID or user principal name.
+	/// # Errors
+	/// Returns `GraphError::Api` with 404 if a user principal name is unknown and with 412 if the task is outdated.
+	pub async fn automation_unassign_task(&self, identity: Identity<'_>, task: &PlannerTask, users: &[&str]) -> Result<PlannerTask, GraphError> {
+		let mut assignments = serde_json::Map::new();
+		for user_id in self.automation_user_ids(identity, users).await? {
+			assignments.insert(user_id, serde_json::Value::Null);
+		}
+		self.patch_task_assignments(identity, task, assignments).await
+	}
+
+	async fn patch_task_assignments(&self, identity: Identity<'_>, task: &PlannerTask, assignments: serde_json::Map<String, serde_json::Value>) -> Result<PlannerTask, GraphError> {
+		let etag = task.odata_etag.as_deref().ok_or(GraphError::MissingField("Task ETag"))?;
+		let url = self.config.beta_url(&format!("/planner/tasks/{}", task.id));
+		let body = json!({ "assignments": assignments });
+		let res = self.send(self.client.patch(url).header(IF_MATCH, etag).header("Prefer", "return=representation").json(&body).bearer_auth(self.bearer_token(identity).await?)).await?;
+		request::json(res).await
+	}
+
+	/// The object ID of a user given by ID or user principal name. (e.g. "jane.doe@contoso.com")
+	/// # Errors
+	/// Returns `GraphError::Api` with 404 if no user has that user principal name.
+	pub async fn automation_user_id(&self, identity: Identity<'_>, user: &str) -> Result<String, GraphError> {
+		if request::is_guid(user) {
+			return Ok(user.to_owned());
+		}
+		let url = self.config.url(&format!("/users/{}?$select=id", request::encode(user)));
+		let res = self.send(self.client.get(url).bearer_auth(self.bearer_token(identity).await?)).await?;
+		let json = request::json::<serde_json::Value>(res).await?;
+		json.get("id").and_then(serde_json::Value::as_str).map(ToOwned::to_owned).ok_or(GraphError::MissingField("User ID"))
+	}
+
+	async fn automation_user_ids(&self, identity: Identity<'_>, users: &[impl AsRef<str> + Sync]) -> Result<Vec<String>, GraphError> {
+		let mut user_ids = Vec::with_capacity(users.len());
+		for user in users {
+			user_ids.push(self.automation_user_id(identity, user.as_ref()).await?);
+		}
+		Ok(user_ids)
+	}
+
+	/// # Errors
+	/// Returns `GraphError::MissingField` if `task` has no `@odata.etag` and `GraphError::Api` with 412 if it changed since it was read.
+	pub async fn automation_delete_task(&self, identity: Identity<'_>, task: &PlannerTask) -> Result<(), GraphError> {
+		let etag = task.odata_etag.as_deref().ok_or(GraphError::MissingField("Task ETag"))?;
+		let url = self.config.beta_url(&format!("/planner/tasks/{}", task.id));
+		self.send(self.client.delete(url).header(IF_MATCH, etag).bearer_auth(self.bearer_token(identity).await?)).await?;
+		Ok(())
+	}
+
+	/// The description, checklist and references of a task.
+	/// # Errors
+	/// Returns `GraphError::Api` with 404 if the task does not exist.
+	pub async fn automation_get_task_details(&self, identity: Identity<'_>, task_id: &str) -> Result<PlannerTaskDetails, GraphError> {
+		let res = self.send(self.client.get(self.config.beta_url(&format!("/planner/tasks/{task_id}/details"))).bearer_auth(self.bearer_token(identity).await?)).await?;
+		request::json(res).await
+	}
+
+	/// Change `details` as they were last read; like tasks, Planner rejects the update with 412 if they changed since.
+	/// # Errors
+	/// Returns `GraphError::MissingField` if `details` has no `@odata.etag` and `GraphError::Api` with 412 if they are outdated.
+	pub async fn automation_update_task_details(&self, identity: Identity<'_>, details: &PlannerTaskDetails, update: &PlannerTaskDetailsUpdate) -> Result<PlannerTaskDetails, GraphError> {
+		let etag = details.odata_etag.as_deref().ok_or(GraphError::MissingField("Task details ETag"))?;
+		let url = self.config.beta_url(&format!("/planner/tasks/{}/details", details.id));
+		let res = self.send(self.client.patch(url).header(IF_MATCH, etag).header("Prefer", "return=representation").json(update).bearer_auth(self.bearer_token(identity).await?)).await?;
+		request::json(res).await
+	}
+
+	/// # Errors
+	/// Returns `GraphError::Api` if Graph rejects the Planner app install or the new tab.
+	pub async fn automation_add_plan_tab_to_teams_channel(&self, identity: Identity<'_>, tab_name: &str, team: Team, channel: Channel, plan: Plan) -> Result<TeamsTab, GraphError> {
+		// add app to team; Graph answers 409 when the Planner app is already installed, which is fine
+		let planner_app = self.config.url("/appCatalogs/teamsApps/com.microsoft.teamspace.tab.planner");
 		let body = json!({
-				"teamsApp@odata.bind":"https://graph.microsoft.com/v1.0/appCatalogs/teamsApps/com.microsoft.teamspace.tab.planner"
+				"teamsApp@odata.bind": planner_app
 		});
-		let url = format!("https://graph.microsoft.com/beta/teams/{}/installedApps", team.id);
-
-		let res = client.post(url).json(&body).bearer_auth(&self.token.access_token).send().await;
-		match res {
-			Ok(res) => {
-				let json = res.json::<serde_json::Value>().await;
-				match json {
-					Ok(_) => (),
-					Err(err) => return Err(err.to_string()),
-				}
-			}
-			Err(err) => return Err(err.to_string()),
+		let url = self.config.beta_url(&format!("/teams/{}/installedApps", team.id));
+
+		match self.send(self.client.post(url).json(&body).bearer_auth(self.bearer_token(identity).await?)).await {
+			Ok(_) | Err(GraphError::Api { status: StatusCode::CONFLICT, .. }) => (),
+			Err(err) => return Err(err),
 		}
 
 		// add tab to channel
-		let entity_id = format!("tt.c_{}_p_{}", channel.id.clone().ok_or("Channel ID not found")?, plan.id);
+		let channel_id = channel.id.ok_or(GraphError::MissingField("Channel ID"))?;
+		let entity_id = format!("tt.c_{}_p_{}", channel_id, plan.id);
 		let content_url = format!("https://tasks.teams.microsoft.com/teamsui/{{tid}}/Home/PlannerFrame?page=7&auth_pvr=OrgId&auth_upn={{userPrincipalName}}&groupId={{groupId}}&planId={}&channelId={{channelId}}&entityId={{entityId}}&tid={{tid}}&userObjectId={{userObjectId}}&subEntityId={{subEntityId}}&sessionId={{sessionId}}&theme={{theme}}&mkt={{locale}}&ringId={{ringId}}&PlannerRouteHint={{tid}}&tabVersion=20200228.1_s", &plan.id);
 		let remove_url = format!("https://tasks.teams.microsoft.com/teamsui/{{tid}}/Home/PlannerFrame?page=13&auth_pvr=OrgId&auth_upn={{userPrincipalName}}&groupId={{groupId}}&planId={}&channelId={{channelId}}&entityId={{entityId}}&tid={{tid}}&userObjectId={{userObjectId}}&subEntityId={{subEntityId}}&sessionId={{sessionId}}&theme={{theme}}&mkt={{locale}}&ringId={{ringId}}&PlannerRouteHint={{tid}}&tabVersion=20200228.1_s", &plan.id);
 		let web_url = format!("https://tasks.office.com/{{tid}}/Home/PlanViews/@{}?Type=PlanLink&Channel=TeamsTab", &plan.id);
 		let body = json!({
 				"displayName": tab_name,
-				"teamsApp@odata.bind": "https://graph.microsoft.com/v1.0/appCatalogs/teamsApps/com.microsoft.teamspace.tab.planner",
+				"teamsApp@odata.bind": planner_app,
 				"configuration": {
 					"entityId": entity_id,
 					"contentUrl": content_url,
@@ -532,19 +571,22 @@ impl MSGraph {
 					"websiteUrl": web_url,
 				}
 		});
-		let url = format!("https://graph.microsoft.com/v1.0/teams/{}/channels/{}/tabs", team.id, channel.id.ok_or("Channel ID not found")?);
-		let res = client.post(url).json(&body).bearer_auth(&self.token.access_token).send().await;
-
-		match res {
-			Ok(res) => {
-				let res = res.json::<serde_json::Value>().await.map_err(|e| e.to_string())?;
-				let res = serde_json::from_value::<TeamsTab>(res);
-				match res {
-					Ok(tab) => Ok(tab),
-					Err(err) => Err(err.to_string()),
-				}
-			}
-			Err(err) => Err(err.to_string()),
-		}
+		let url = self.config.url(&format!("/teams/{}/channels/{}/tabs", team.id, channel_id));
+		let res = self.send(self.client.post(url).json(&body).bearer_auth(self.bearer_token(identity).await?)).await?;
+		request::json(res).await
+	}
+}
+
+///
+/// Assignments for `user_ids` in that order, chained after the assignee with order hint `last`.
+///
+fn assignments_after(last: Option<&str>, user_ids: Vec<String>) -> serde_json::Map<String, serde_json::Value> {
+	let mut previous = last.map(ToOwned::to_owned);
+	let mut assignments = serde_json::Map::new();
+	for user_id in user_ids {
+		let assignment = PlannerAssignment::after(previous.as_deref());
+		previous.clone_from(&assignment.order_hint);
+		assignments.insert(user_id, json!(assignment));
 	}
+	assignments
 }
@@ -1,4 +1,5 @@
-use rocket::http::Status;
+#[cfg(feature = "rocket")]
+use rocket::{http::Status, serde::json::Json};
 use serde::{Deserialize, Serialize};
 
 /* {
@@ -45,20 +46,29 @@ pub struct Me {
 	pub id: String,
 }
 
+///
+/// The current user's photo with the media type Graph sent it as, e.g. "image/jpeg" or "image/png".
+///
+#[derive(Debug, Clone)]
+pub struct Photo {
+	pub content_type: Option<String>,
+	pub bytes: Vec<u8>,
+}
+
+#[cfg(feature = "rocket")]
 pub struct MeResponse {
 	pub me: Option<Me>,
 	pub error: Option<String>,
 	pub status: Status,
 }
 
+#[cfg(feature = "rocket")]
 impl<'r> rocket::response::Responder<'r, 'static> for MeResponse {
 	fn respond_to(self, req: &'r rocket::Request<'_>) -> rocket::response::Result<'static> {
-		if let Some(me) = self.me {
-			let json = serde_json::to_string(&me).unwrap();
-			rocket::response::Response::build_from(json.respond_to(req).unwrap()).status(self.status).ok()
-		} else {
-			let json = serde_json::to_string(&self.error).unwrap();
-			rocket::response::Response::build_from(json.respond_to(req).unwrap()).status(self.status).ok()
-		}
+		let response = match self.me {
+			Some(me) => Json(me).respond_to(req)?,
+			None => Json(crate::GraphErrorResponse { error: crate::GraphErrorBody { code: Some(self.status.reason_lossy().to_owned()), message: self.error, inner_error: None } }).respond_to(req)?,
+		};
+		rocket::response::Response::build_from(response).status(self.status).ok()
 	}
 }
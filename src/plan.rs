@@ -2,9 +2,10 @@
 
 use std::{collections::HashMap, str::FromStr};
 
-use rocket::FromForm;
 use serde::{Deserialize, Serialize};
 
+use crate::GraphCollection;
+
 /*
 {
 	"@odata.context": "https://graph.microsoft.com/v1.0/$metadata#planner/plans/$entity",
@@ -75,16 +76,7 @@ pub struct Container {
 	pub url: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PlanCollection {
-	#[serde(rename = "@odata.context")]
-	pub odata_context: Option<String>,
-	#[serde(rename = "@odata.nextLink")]
-	pub odata_next_link: Option<String>,
-	#[serde(rename = "@odata.count")]
-	pub odata_count: Option<i64>,
-	pub value: Option<Vec<Plan>>,
-}
+pub type PlanCollection = GraphCollection<Plan>;
 
 /*
 {
@@ -98,22 +90,23 @@ pub struct PlanCollection {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bucket {
 	#[serde(rename = "creationSource")]
-	creation_source: Option<CreationSource>,
-	id: Option<String>,
-	name: Option<String>,
+	pub creation_source: Option<CreationSource>,
+	pub id: Option<String>,
+	pub name: Option<String>,
 	#[serde(rename = "orderHint")]
-	order_hint: Option<String>,
+	pub order_hint: Option<String>,
 	#[serde(rename = "planId")]
-	plan_id: Option<String>,
+	pub plan_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreationSource {
 	#[serde(rename = "@odata.type")]
-	odata_type: Option<String>,
+	pub odata_type: Option<String>,
 }
 
-#[derive(FromForm, Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "rocket", derive(rocket::FromForm))]
 pub struct CreatePlanForm {
 	pub plan_name: String,
 	pub plan_template: String,
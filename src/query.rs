@@ -0,0 +1,134 @@
+use crate::request;
+
+///
+/// `OData` system query options for Graph list endpoints.
+///
+/// ```ignore
+/// let query = ODataQuery::new().filter(&format!("displayName eq {}", ODataQuery::literal("New York"))).select(&["id", "displayName"]).top(50);
+/// let groups = graph.automation_groups_with_teams(Identity::App, &query).await?;
+/// ```
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ODataQuery {
+	filter: Option<String>,
+	select: Vec<String>,
+	expand: Vec<String>,
+	top: Option<u32>,
+	orderby: Vec<String>,
+	search: Option<String>,
+	count: bool,
+}
+
+impl ODataQuery {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Quote a string for use inside `$filter`, doubling embedded single quotes. (e.g. `O'Brien` becomes `'O''Brien'`)
+	#[must_use]
+	pub fn literal(value: &str) -> String {
+		format!("'{}'", value.replace('\'', "''"))
+	}
+
+	/// Set `$filter`, replacing any previous filter.
+	#[must_use]
+	pub fn filter(mut self, filter: &str) -> Self {
+		self.filter = Some(filter.to_owned());
+		self
+	}
+
+	/// Combine `filter` with any existing `$filter` using `and`.
+	#[must_use]
+	pub fn and_filter(mut self, filter: &str) -> Self {
+		self.filter = Some(self.filter.map_or_else(|| filter.to_owned(), |existing| format!("({existing}) and ({filter})")));
+		self
+	}
+
+	#[must_use]
+	pub fn select(mut self, fields: &[&str]) -> Self {
+		self.select.extend(fields.iter().map(|field| (*field).to_owned()));
+		self
+	}
+
+	#[must_use]
+	pub fn expand(mut self, relations: &[&str]) -> Self {
+		self.expand.extend(relations.iter().map(|relation| (*relation).to_owned()));
+		self
+	}
+
+	#[must_use]
+	pub const fn top(mut self, top: u32) -> Self {
+		self.top = Some(top);
+		self
+	}
+
+	/// Add a sort key, e.g. "displayName" or "createdDateTime desc".
+	#[must_use]
+	pub fn orderby(mut self, field: &str) -> Self {
+		self.orderby.push(field.to_owned());
+		self
+	}
+
+	/// Set `$search`, e.g. "displayName:Houston". The value is wrapped in the double quotes Graph requires.
+	#[must_use]
+	pub fn search(mut self, search: &str) -> Self {
+		self.search = Some(format!("\"{}\"", search.trim_matches('"')));
+		self
+	}
+
+	#[must_use]
+	pub const fn count(mut self, count: bool) -> Self {
+		self.count = count;
+		self
+	}
+
+	/// Whether Graph needs the `ConsistencyLevel: eventual` header for this query, which is the case for `$count` and `$search`.
+	#[must_use]
+	pub const fn needs_eventual_consistency(&self) -> bool {
+		self.count || self.search.is_some()
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self == &Self::default()
+	}
+
+	/// The encoded query string without the leading `?`.
+	#[must_use]
+	pub fn to_query_string(&self) -> String {
+		let mut params = Vec::new();
+		if let Some(filter) = &self.filter {
+			params.push(("$filter", filter.clone()));
+		}
+		if !self.select.is_empty() {
+			params.push(("$select", self.select.join(",")));
+		}
+		if !self.expand.is_empty() {
+			params.push(("$expand", self.expand.join(",")));
+		}
+		if let Some(top) = self.top {
+			params.push(("$top", top.to_string()));
+		}
+		if !self.orderby.is_empty() {
+			params.push(("$orderby", self.orderby.join(",")));
+		}
+		if let Some(search) = &self.search {
+			params.push(("$search", search.clone()));
+		}
+		if self.count {
+			params.push(("$count", "true".to_owned()));
+		}
+		params.iter().map(|(key, value)| format!("{key}={}", request::encode(value))).collect::<Vec<_>>().join("&")
+	}
+
+	/// Append this query to `url`, which may already carry query parameters.
+	#[must_use]
+	pub fn apply(&self, url: &str) -> String {
+		if self.is_empty() {
+			return url.to_owned();
+		}
+		let separator = if url.contains('?') { '&' } else { '?' };
+		format!("{url}{separator}{}", self.to_query_string())
+	}
+}
@@ -0,0 +1,108 @@
+#[cfg(feature = "rocket")]
+use std::ops::Deref;
+use std::{collections::HashMap, sync::Arc};
+
+#[cfg(feature = "rocket")]
+use rocket::{
+	http::Status,
+	request::{FromRequest, Outcome},
+	Request,
+};
+
+use crate::{token, GraphError, MSGraph, User};
+
+///
+/// `MSGraph` clients for several Entra tenants, each with its own credentials and tokens.
+/// Clients are keyed by tenant ID and may be given aliases, e.g. the subsidiary's name.
+///
+/// ```ignore
+/// let registry = MSGraphRegistry::new()
+///     .register(MSGraph::from_provider(&KeyVaultCredentials::new("vault-us", "client-id", "client-secret", "tenant-id")).await?)
+///     .register(MSGraph::from_provider(&KeyVaultCredentials::new("vault-de", "client-id", "client-secret", "tenant-id")).await?)
+///     .alias("us", us_tenant_id);
+/// rocket::build().manage(registry)
+/// ```
+///
+#[derive(Default)]
+pub struct MSGraphRegistry {
+	clients: HashMap<String, Arc<MSGraph>>,
+	aliases: HashMap<String, String>,
+}
+
+///
+/// Request guard for the `MSGraph` of the signed-in user's tenant, taken from the `tid` claim of their token.
+///
+/// Needs an `MSGraphRegistry` in managed state; forwards when there is no signed-in user and fails with 403 when their tenant is not registered.
+///
+#[cfg(feature = "rocket")]
+pub struct TenantGraph(pub Arc<MSGraph>);
+
+impl MSGraphRegistry {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add `graph` under its tenant ID, replacing any client already registered for that tenant.
+	#[must_use]
+	pub fn register(mut self, graph: MSGraph) -> Self {
+		self.clients.insert(graph.tenant_id().to_owned(), Arc::new(graph));
+		self
+	}
+
+	/// Make `alias` resolve to the client of `tenant_id`.
+	#[must_use]
+	pub fn alias(mut self, alias: &str, tenant_id: &str) -> Self {
+		self.aliases.insert(alias.to_owned(), tenant_id.to_owned());
+		self
+	}
+
+	/// The client registered for a tenant ID or alias.
+	#[must_use]
+	pub fn get(&self, tenant: &str) -> Option<Arc<MSGraph>> {
+		let tenant_id = self.aliases.get(tenant).map_or(tenant, String::as_str);
+		self.clients.get(tenant_id).cloned()
+	}
+
+	/// The client for the tenant `user` signed in with.
+	///
+	/// # Errors
+	/// Returns `GraphError::MissingField` if the user's token has no `tid` claim and `GraphError::NotFound` if that tenant is not registered.
+	pub fn for_user(&self, user: &User) -> Result<Arc<MSGraph>, GraphError> {
+		let tenant_id = token::claim(&user.token.ms_token.access_token, "tid").ok_or(GraphError::MissingField("Token tenant ID"))?;
+		self.get(&tenant_id).ok_or_else(|| GraphError::NotFound(format!("Graph client for tenant {tenant_id}")))
+	}
+
+	/// Tenant IDs with a registered client.
+	pub fn tenants(&self) -> impl Iterator<Item = &str> {
+		self.clients.keys().map(String::as_str)
+	}
+}
+
+#[cfg(feature = "rocket")]
+impl Deref for TenantGraph {
+	type Target = MSGraph;
+
+	fn deref(&self) -> &MSGraph {
+		&self.0
+	}
+}
+
+#[cfg(feature = "rocket")]
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for TenantGraph {
+	type Error = GraphError;
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		let user = match request.guard::<User>().await {
+			Outcome::Success(user) => user,
+			Outcome::Forward(status) | Outcome::Error((status, ..)) => return Outcome::Forward(status),
+		};
+		let Some(registry) = request.rocket().state::<MSGraphRegistry>() else { return Outcome::Error((Status::InternalServerError, GraphError::NotFound("MSGraphRegistry in managed state".to_owned()))) };
+
+		match registry.for_user(&user) {
+			Ok(graph) => Outcome::Success(Self(graph)),
+			Err(err) => Outcome::Error((Status::Forbidden, err)),
+		}
+	}
+}
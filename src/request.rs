@@ -0,0 +1,90 @@
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::{Client, Request, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+
+use crate::{GraphError, MSGraph, MSGraphConfig, RetryEvent};
+
+/// Everything except RFC 3986 unreserved characters is encoded in query values.
+const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+impl MSGraph {
+	/// Send a request under this instance's retry policy and cassette.
+	pub(crate) async fn send(&self, request: RequestBuilder) -> Result<Response, GraphError> {
+		send(&self.config, request).await
+	}
+}
+
+///
+/// Send a request, retrying as `config.retry` allows, and turn any non-success response into a `GraphError::Api`.
+/// With a cassette configured, each attempt is recorded or replayed.
+///
+pub async fn send(config: &MSGraphConfig, request: RequestBuilder) -> Result<Response, GraphError> {
+	let retry = &config.retry;
+	let (client, request) = request.build_split();
+	let request = request.map_err(|source| GraphError::Request { url: source.url().map(ToString::to_string).unwrap_or_default(), source })?;
+	let retryable = retry.allows(request.method());
+
+	let mut retries = 0;
+	loop {
+		// Streaming bodies cannot be cloned, so such requests are sent only once.
+		let attempt = if retryable && retries < retry.max_retries { request.try_clone() } else { None };
+		let Some(attempt) = attempt else { return execute(config, &client, request).await };
+
+		let err = match execute(config, &client, attempt).await {
+			Ok(res) => return Ok(res),
+			Err(err) => err,
+		};
+		retries += 1;
+		let Some(delay) = retry.delay(&err, retries) else { return Err(err) };
+		retry.notify(&RetryEvent { method: request.method().clone(), url: request.url().to_string(), retry: retries, delay, status: err.status() });
+		tokio::time::sleep(delay).await;
+	}
+}
+
+async fn execute(config: &MSGraphConfig, client: &Client, request: Request) -> Result<Response, GraphError> {
+	let url = request.url().to_string();
+	let res = match &config.cassette {
+		Some(cassette) => cassette.execute(client, request).await?,
+		None => client.execute(request).await.map_err(|source| GraphError::Request { url: url.clone(), source })?,
+	};
+	if res.status().is_success() {
+		Ok(res)
+	} else {
+		Err(GraphError::from_response(url, res).await)
+	}
+}
+
+///
+/// Read a successful response body as JSON.
+///
+pub async fn json<T: DeserializeOwned>(res: Response) -> Result<T, GraphError> {
+	let url = res.url().to_string();
+	let body = res.text().await.map_err(|source| GraphError::Request { url: url.clone(), source })?;
+	serde_json::from_str(&body).map_err(|source| GraphError::Deserialize { url, source })
+}
+
+///
+/// Percent-encode a query parameter value.
+///
+pub fn encode(value: &str) -> String {
+	utf8_percent_encode(value, QUERY_VALUE).to_string()
+}
+
+///
+/// Whether `value` is a GUID, i.e. an object ID rather than a name or user principal name.
+///
+pub fn is_guid(value: &str) -> bool {
+	let groups = value.split('-').collect::<Vec<_>>();
+	groups.iter().map(|group| group.len()).eq([8, 4, 4, 4, 12]) && groups.iter().all(|group| group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+///
+/// The only item of a name lookup, or `GraphError::NotFound` / `GraphError::Ambiguous` when there are none or several.
+///
+pub fn single<T>(mut items: Vec<T>, what: String) -> Result<T, GraphError> {
+	match items.len() {
+		0 => Err(GraphError::NotFound(what)),
+		1 => Ok(items.remove(0)),
+		count => Err(GraphError::Ambiguous { what, count }),
+	}
+}
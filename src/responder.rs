@@ -0,0 +1,79 @@
+use rocket::{
+	http::{Header, Status},
+	response::{Responder, Response, Result},
+	serde::json::Json,
+	Request,
+};
+use serde::Serialize;
+
+use crate::{GraphError, GraphErrorBody, GraphErrorResponse, GraphInnerError};
+
+///
+/// A Graph result as a JSON response: the value with 200, or the error as `GraphError` responds.
+///
+/// ```ignore
+/// #[get("/teams/<name>")]
+/// async fn team(graph: &MSGraph, name: &str) -> GraphJson<Team> {
+///     graph.automation_team_by_name(Identity::App, name.to_owned()).await.into()
+/// }
+/// ```
+///
+#[derive(Debug)]
+pub struct GraphJson<T>(pub std::result::Result<T, GraphError>);
+
+impl GraphError {
+	/// The status a web handler should answer with for this error.
+	/// Graph's 400, 403, 404, 409, 412, 429 and 503 pass through, failed credentials give 503 and any other upstream failure gives 502.
+	#[must_use]
+	pub const fn http_status(&self) -> Status {
+		match self {
+			Self::NotFound(_) => Status::NotFound,
+			Self::Ambiguous { .. } => Status::Conflict,
+			Self::Api { status, .. } if matches!(status.as_u16(), 400 | 403 | 404 | 409 | 412 | 429 | 503) => Status::new(status.as_u16()),
+			Self::KeyVault { .. } | Self::Credentials { .. } => Status::ServiceUnavailable,
+			Self::BatchTooLarge(_) | Self::Cassette { .. } => Status::InternalServerError,
+			Self::Api { .. } | Self::Request { .. } | Self::Deserialize { .. } | Self::MissingField(_) => Status::BadGateway,
+		}
+	}
+
+	/// The body sent for this error, shaped like Graph's own error envelope.
+	#[must_use]
+	pub fn to_response(&self) -> GraphErrorResponse {
+		// Graph's own message rather than `Display`, which names the Graph url.
+		let (code, message, request_id) = match self {
+			Self::Api { code, message, request_id, .. } => (code.clone(), message.clone(), request_id.clone()),
+			_ => (None, None, None),
+		};
+		let code = code.unwrap_or_else(|| self.http_status().reason_lossy().to_owned());
+		let message = message.unwrap_or_else(|| self.to_string());
+		let inner_error = request_id.map(|request_id| GraphInnerError { request_id: Some(request_id), client_request_id: None, date: None });
+		GraphErrorResponse { error: GraphErrorBody { code: Some(code), message: Some(message), inner_error } }
+	}
+}
+
+/// Answers with `http_status`, the `to_response` body and, when Graph asked for it, a `Retry-After` header.
+impl<'r> Responder<'r, 'static> for GraphError {
+	fn respond_to(self, req: &'r Request<'_>) -> Result<'static> {
+		let mut response = Response::build_from(Json(self.to_response()).respond_to(req)?);
+		response.status(self.http_status());
+		if let Self::Api { retry_after: Some(retry_after), .. } = &self {
+			response.header(Header::new("Retry-After", retry_after.as_secs().to_string()));
+		}
+		response.ok()
+	}
+}
+
+impl<'r, T: Serialize> Responder<'r, 'static> for GraphJson<T> {
+	fn respond_to(self, req: &'r Request<'_>) -> Result<'static> {
+		match self.0 {
+			Ok(value) => Json(value).respond_to(req),
+			Err(err) => err.respond_to(req),
+		}
+	}
+}
+
+impl<T> From<std::result::Result<T, GraphError>> for GraphJson<T> {
+	fn from(result: std::result::Result<T, GraphError>) -> Self {
+		Self(result)
+	}
+}
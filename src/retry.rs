@@ -0,0 +1,133 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use rand::Rng;
+use reqwest::{Method, StatusCode};
+
+use crate::GraphError;
+
+///
+/// When and how often `MSGraph` re-sends a failed request.
+///
+/// Throttled (429) and unavailable (503, 504) responses, connection failures and timeouts are retried.
+/// A `Retry-After` header from Graph wins over the computed backoff; one longer than `max_delay` is not waited for and fails the request instead.
+///
+#[derive(Clone)]
+pub struct RetryPolicy {
+	/// Retries after the first attempt; 0 disables retrying.
+	pub max_retries: u32,
+
+	/// Backoff before the first retry; doubled for every further retry.
+	pub base_delay: Duration,
+
+	/// Upper bound for any wait, computed or asked for by `Retry-After`.
+	pub max_delay: Duration,
+
+	/// Also retry POST and PATCH. Off by default because Graph may have applied a request that timed out.
+	pub retry_non_idempotent: bool,
+
+	on_retry: Option<RetryHook>,
+}
+
+type RetryHook = Arc<dyn Fn(&RetryEvent) + Send + Sync>;
+
+///
+/// Passed to the `RetryPolicy::on_retry` hook before each retry.
+///
+#[derive(Debug, Clone)]
+pub struct RetryEvent {
+	pub method: Method,
+	pub url: String,
+
+	/// 1 for the first retry.
+	pub retry: u32,
+
+	/// How long `MSGraph` waits before sending the request again.
+	pub delay: Duration,
+
+	/// The status Graph answered with, or `None` for connection failures and timeouts.
+	pub status: Option<StatusCode>,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self { max_retries: 3, base_delay: Duration::from_millis(500), max_delay: Duration::from_secs(30), retry_non_idempotent: false, on_retry: None }
+	}
+}
+
+impl fmt::Debug for RetryPolicy {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RetryPolicy").field("max_retries", &self.max_retries).field("base_delay", &self.base_delay).field("max_delay", &self.max_delay).field("retry_non_idempotent", &self.retry_non_idempotent).field("on_retry", &self.on_retry.is_some()).finish()
+	}
+}
+
+impl RetryPolicy {
+	/// Never retry.
+	#[must_use]
+	pub fn none() -> Self {
+		Self { max_retries: 0, ..Self::default() }
+	}
+
+	#[must_use]
+	pub const fn max_retries(mut self, max_retries: u32) -> Self {
+		self.max_retries = max_retries;
+		self
+	}
+
+	#[must_use]
+	pub const fn base_delay(mut self, base_delay: Duration) -> Self {
+		self.base_delay = base_delay;
+		self
+	}
+
+	#[must_use]
+	pub const fn max_delay(mut self, max_delay: Duration) -> Self {
+		self.max_delay = max_delay;
+		self
+	}
+
+	#[must_use]
+	pub const fn retry_non_idempotent(mut self, retry_non_idempotent: bool) -> Self {
+		self.retry_non_idempotent = retry_non_idempotent;
+		self
+	}
+
+	/// Call `hook` before every retry, e.g. to log or count throttling.
+	#[must_use]
+	pub fn on_retry(mut self, hook: impl Fn(&RetryEvent) + Send + Sync + 'static) -> Self {
+		self.on_retry = Some(Arc::new(hook));
+		self
+	}
+
+	/// Whether requests with `method` may be sent more than once.
+	#[must_use]
+	pub const fn allows(&self, method: &Method) -> bool {
+		self.retry_non_idempotent || matches!(*method, Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS)
+	}
+
+	/// How long to wait before retry number `retry` (1-based) after `err`, or `None` if `err` is not worth retrying.
+	#[must_use]
+	pub fn delay(&self, err: &GraphError, retry: u32) -> Option<Duration> {
+		if retry > self.max_retries {
+			return None;
+		}
+		match err {
+			GraphError::Api { status: StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT, retry_after: Some(retry_after), .. } => (*retry_after <= self.max_delay).then_some(*retry_after),
+			GraphError::Api { status: StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT, .. } => Some(self.backoff(retry)),
+			GraphError::Request { source, .. } if source.is_timeout() || source.is_connect() => Some(self.backoff(retry)),
+			_ => None,
+		}
+	}
+
+	/// Exponential backoff with jitter: a random delay between half and all of `base_delay * 2^(retry - 1)`, capped at `max_delay`.
+	fn backoff(&self, retry: u32) -> Duration {
+		let ceiling = self.base_delay.saturating_mul(2_u32.saturating_pow(retry.saturating_sub(1))).min(self.max_delay);
+		let half = ceiling / 2;
+		half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+	}
+
+	pub(crate) fn notify(&self, event: &RetryEvent) {
+		if let Some(hook) = &self.on_retry {
+			hook(event);
+		}
+	}
+}
@@ -0,0 +1,94 @@
+//!
+//! Mountable Rocket endpoints for the crate's operations, enabled by the `routes` feature.
+//!
+//! They take `&MSGraph` from `MSGraph::fairing` and the signed-in `User`, and call Graph as that user with their token, so tenant policies that block app-only calls (e.g. shared-channel creation) do not apply.
+//! The user's token must be issued for Graph and carry the delegated permissions each operation needs.
+//!
+//! ```ignore
+//! rocket::build().attach(MSGraph::fairing()).mount("/graph", eggersmann_app_server_ms_graph::routes::all())
+//! ```
+//!
+
+use std::io;
+
+use rocket::{
+	data::{self, Data, FromData, ToByteUnit},
+	form::Form,
+	http::{ContentType, Status},
+	Request, Route,
+};
+
+use crate::{CreatePlanForm, CreateSharedChannelForm, CreateSharedChannelResponse, GraphError, GraphJson, Identity, MSGraph, Me, Plan, User};
+
+/// The Rocket limit for `Upload` bodies, e.g. `limits = { ms-graph-upload = "256 MiB" }` in `Rocket.toml`.
+/// Rocket predefines no limit by this name, so `DEFAULT_UPLOAD_LIMIT_MIB` applies until one is set.
+pub const UPLOAD_LIMIT: &str = "ms-graph-upload";
+
+/// Uploads larger than this are rejected with 413 unless the `ms-graph-upload` limit says otherwise.
+const DEFAULT_UPLOAD_LIMIT_MIB: u64 = 64;
+
+///
+/// A raw request body read up to the `ms-graph-upload` limit; larger bodies are rejected with 413.
+///
+pub struct Upload(pub Vec<u8>);
+
+/// Every route in this module.
+#[must_use]
+pub fn all() -> Vec<Route> {
+	rocket::routes![me, me_photo, create_shared_channel, create_plan, upload_to_drive]
+}
+
+/// GET `/me`
+#[rocket::get("/me")]
+async fn me(graph: &MSGraph, user: User) -> GraphJson<Me> {
+	graph.me(user).await.into()
+}
+
+/// GET `/me/photo`, served with the media type Graph reports for it.
+#[rocket::get("/me/photo")]
+async fn me_photo(graph: &MSGraph, user: User) -> Result<(ContentType, Vec<u8>), GraphError> {
+	let photo = graph.me_photo(user).await?;
+	let content_type = photo.content_type.as_deref().and_then(ContentType::parse_flexible).unwrap_or(ContentType::Binary);
+	Ok((content_type, photo.bytes))
+}
+
+/// POST `/teams/shared-channels` with a `CreateSharedChannelForm`
+#[rocket::post("/teams/shared-channels", data = "<data>")]
+async fn create_shared_channel(graph: &MSGraph, user: User, data: Form<CreateSharedChannelForm>) -> Result<CreateSharedChannelResponse, GraphError> {
+	let (team, channel, general_channel) = graph.automation_teams_create_shared_channel(Identity::User(&user), &data).await?;
+	Ok(CreateSharedChannelResponse { channel: Some(channel), team: Some(team), general_channel: Some(general_channel), error: None, status: "success".to_owned() })
+}
+
+/// POST `/teams/<team_name>/plans` with a `CreatePlanForm`; the plan gets the buckets of its template.
+#[rocket::post("/teams/<team_name>/plans", data = "<data>")]
+async fn create_plan(graph: &MSGraph, user: User, team_name: &str, data: Form<CreatePlanForm>) -> GraphJson<Plan> {
+	let plan = data.to_create_plan();
+	let identity = Identity::User(&user);
+	let result = async {
+		let created_plan = graph.automation_create_plan(identity, plan.plan_name, team_name.to_owned()).await?;
+		let spec = plan.plan_template.to_spec();
+		graph.automation_add_buckets_to_plan(identity, created_plan.id.clone(), spec.buckets.into_keys().collect()).await?;
+		Ok(created_plan)
+	};
+	result.await.into()
+}
+
+/// PUT `/sites/<site_name>/drives/<drive_name>/<file_name>` with the file as body, up to the `ms-graph-upload` limit.
+#[rocket::put("/sites/<site_name>/drives/<drive_name>/<file_name>", data = "<data>")]
+async fn upload_to_drive(graph: &MSGraph, user: User, site_name: &str, drive_name: &str, file_name: &str, data: Upload) -> Result<(ContentType, String), GraphError> {
+	graph.put_item_in_site_drive(Identity::User(&user), site_name, drive_name, file_name, data.0).await.map(|json| (ContentType::JSON, json))
+}
+
+#[rocket::async_trait]
+impl<'r> FromData<'r> for Upload {
+	type Error = io::Error;
+
+	async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+		let limit = req.limits().get(UPLOAD_LIMIT).unwrap_or_else(|| DEFAULT_UPLOAD_LIMIT_MIB.mebibytes());
+		match data.open(limit).into_bytes().await {
+			Ok(bytes) if bytes.is_complete() => data::Outcome::Success(Self(bytes.into_inner())),
+			Ok(_) => data::Outcome::Error((Status::PayloadTooLarge, io::Error::other(format!("upload exceeds the {UPLOAD_LIMIT} limit of {limit}")))),
+			Err(err) => data::Outcome::Error((Status::BadRequest, err)),
+		}
+	}
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::GraphCollection;
+
 /* {
 		"createdDateTime": "2018-02-09T22:30:55Z",
 		"displayName": "Andrea Villamarin",
@@ -39,9 +41,4 @@ pub struct SiteCollect {
 	pub hostname: Option<String>,
 }
 
-#[derive(Deserialize, Debug, Clone, Serialize)]
-pub struct SiteCollection {
-	#[serde(rename = "@odata.context")]
-	pub odata_context: Option<String>,
-	pub value: Vec<Site>,
-}
+pub type SiteCollection = GraphCollection<Site>;
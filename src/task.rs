@@ -0,0 +1,501 @@
+use std::collections::HashMap;
+
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+
+use crate::{CreatedBy, GraphCollection};
+
+/*
+{
+	"@odata.etag": "W/\"JzEtVGFzayAgQEBAQEBAQEBAQEBAQEBAWCc=\"",
+	"planId": "-GIcbyTe1U-HMRIICi4gRmUAHMV2",
+	"bucketId": "gcrYAaAkgU2EQUvpkNNXLGQAGTtu",
+	"title": "Measure showroom",
+	"orderHint": "8585269241124331036",
+	"assigneePriority": "",
+	"percentComplete": 50,
+	"startDateTime": null,
+	"createdDateTime": "2023-02-01T16:10:27.3512349Z",
+	"dueDateTime": "2023-02-10T17:00:00Z",
+	"hasDescription": false,
+	"previewType": "automatic",
+	"completedDateTime": null,
+	"completedBy": null,
+	"referenceCount": 0,
+	"checklistItemCount": 0,
+	"activeChecklistItemCount": 0,
+	"conversationThreadId": null,
+	"priority": 5,
+	"id": "01gzSlKkIUSUl0DsAAAAAMgAAAAA",
+	"createdBy": {
+		"user": {
+			"displayName": null,
+			"id": "c258b80f-26fb-435d-8ad5-95f588c953e5"
+		}
+	},
+	"appliedCategories": {},
+	"assignments": {}
+}
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannerTask {
+	#[serde(rename = "@odata.etag")]
+	pub odata_etag: Option<String>,
+	#[serde(rename = "planId")]
+	pub plan_id: Option<String>,
+	#[serde(rename = "bucketId")]
+	pub bucket_id: Option<String>,
+	pub title: Option<String>,
+	#[serde(rename = "orderHint")]
+	pub order_hint: Option<String>,
+	#[serde(rename = "assigneePriority")]
+	pub assignee_priority: Option<String>,
+	#[serde(rename = "percentComplete")]
+	pub percent_complete: Option<i32>,
+	#[serde(rename = "startDateTime")]
+	pub start_date_time: Option<String>,
+	#[serde(rename = "createdDateTime")]
+	pub created_date_time: Option<String>,
+	#[serde(rename = "dueDateTime")]
+	pub due_date_time: Option<String>,
+	#[serde(rename = "hasDescription")]
+	pub has_description: Option<bool>,
+	#[serde(rename = "previewType")]
+	pub preview_type: Option<String>,
+	#[serde(rename = "completedDateTime")]
+	pub completed_date_time: Option<String>,
+	#[serde(rename = "completedBy")]
+	pub completed_by: Option<CreatedBy>,
+	#[serde(rename = "referenceCount")]
+	pub reference_count: Option<i32>,
+	#[serde(rename = "checklistItemCount")]
+	pub checklist_item_count: Option<i32>,
+	#[serde(rename = "activeChecklistItemCount")]
+	pub active_checklist_item_count: Option<i32>,
+	#[serde(rename = "conversationThreadId")]
+	pub conversation_thread_id: Option<String>,
+	pub priority: Option<i32>,
+	pub id: String,
+	#[serde(rename = "createdBy")]
+	pub created_by: Option<CreatedBy>,
+	#[serde(rename = "appliedCategories")]
+	pub applied_categories: Option<HashMap<String, bool>>,
+	/// Keyed by the assignee's user ID.
+	pub assignments: Option<HashMap<String, PlannerAssignment>>,
+}
+
+///
+/// An assignee of a task. Built with `PlannerAssignment::new` it is also what Planner expects when adding one.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannerAssignment {
+	#[serde(rename = "@odata.type", skip_serializing_if = "Option::is_none")]
+	pub odata_type: Option<String>,
+	#[serde(rename = "assignedBy", skip_serializing_if = "Option::is_none")]
+	pub assigned_by: Option<CreatedBy>,
+	#[serde(rename = "assignedDateTime", skip_serializing_if = "Option::is_none")]
+	pub assigned_date_time: Option<String>,
+	#[serde(rename = "orderHint", skip_serializing_if = "Option::is_none")]
+	pub order_hint: Option<String>,
+}
+
+pub type PlannerTaskCollection = GraphCollection<PlannerTask>;
+
+/// Planner's priorities run from 0 (highest) to 10 (lowest); the Planner apps show 0 and 1 as urgent.
+pub const PRIORITY_URGENT: i32 = 1;
+
+/// Shown as important for 2 to 4.
+pub const PRIORITY_IMPORTANT: i32 = 3;
+
+/// Shown as medium for 5 to 7; the default for new tasks.
+pub const PRIORITY_MEDIUM: i32 = 5;
+
+/// Shown as low for 8 to 10.
+pub const PRIORITY_LOW: i32 = 9;
+
+///
+/// A task to create in a bucket. Dates are ISO 8601 in UTC. (e.g. "2023-02-10T17:00:00Z")
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePlannerTask {
+	#[serde(rename = "planId")]
+	pub plan_id: String,
+	#[serde(rename = "bucketId")]
+	pub bucket_id: String,
+	pub title: String,
+	#[serde(rename = "startDateTime", skip_serializing_if = "Option::is_none")]
+	pub start_date_time: Option<String>,
+	#[serde(rename = "dueDateTime", skip_serializing_if = "Option::is_none")]
+	pub due_date_time: Option<String>,
+	#[serde(rename = "percentComplete", skip_serializing_if = "Option::is_none")]
+	pub percent_complete: Option<i32>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub priority: Option<i32>,
+	/// User IDs or user principal names; `MSGraph::automation_create_task` resolves them to the `assignments` Planner expects.
+	#[serde(skip)]
+	pub assignees: Vec<String>,
+}
+
+///
+/// The fields of a task to change; fields left `None` are not sent.
+/// `due_date_time` is `Some(None)` to clear the due date.
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlannerTaskUpdate {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub title: Option<String>,
+	#[serde(rename = "dueDateTime", skip_serializing_if = "Option::is_none")]
+	pub due_date_time: Option<Option<String>>,
+	#[serde(rename = "percentComplete", skip_serializing_if = "Option::is_none")]
+	pub percent_complete: Option<i32>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub priority: Option<i32>,
+}
+
+impl CreatePlannerTask {
+	#[must_use]
+	pub fn new(plan_id: &str, bucket_id: &str, title: &str) -> Self {
+		Self {
+			plan_id: plan_id.to_owned(),
+			bucket_id: bucket_id.to_owned(),
+			title: title.to_owned(),
+			start_date_time: None,
+			due_date_time: None,
+			percent_complete: None,
+			priority: None,
+			assignees: Vec::new(),
+		}
+	}
+
+	#[must_use]
+	pub fn start_date_time(mut self, start_date_time: &str) -> Self {
+		self.start_date_time = Some(start_date_time.to_owned());
+		self
+	}
+
+	#[must_use]
+	pub fn due_date_time(mut self, due_date_time: &str) -> Self {
+		self.due_date_time = Some(due_date_time.to_owned());
+		self
+	}
+
+	/// 0 is not started, 100 is completed; the Planner apps show anything in between as in progress.
+	#[must_use]
+	pub const fn percent_complete(mut self, percent_complete: i32) -> Self {
+		self.percent_complete = Some(percent_complete);
+		self
+	}
+
+	/// See `PRIORITY_URGENT` and its siblings.
+	#[must_use]
+	pub const fn priority(mut self, priority: i32) -> Self {
+		self.priority = Some(priority);
+		self
+	}
+
+	/// Assign the task to a user by ID or user principal name. (e.g. `form.owner_id` of a `CreateSharedChannelForm`)
+	#[must_use]
+	pub fn assignee(mut self, user: &str) -> Self {
+		self.assignees.push(user.to_owned());
+		self
+	}
+}
+
+impl PlannerAssignment {
+	/// A new assignment, placed first among the task's assignees.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::after(None)
+	}
+
+	/// A new assignment placed right after the assignee with order hint `previous`, or first if there is none.
+	#[must_use]
+	pub fn after(previous: Option<&str>) -> Self {
+		let order_hint = previous.map_or_else(|| " !".to_owned(), |previous| format!("{previous} !"));
+		Self { odata_type: Some("#microsoft.graph.plannerAssignment".to_owned()), assigned_by: None, assigned_date_time: None, order_hint: Some(order_hint) }
+	}
+}
+
+impl Default for PlannerAssignment {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl PlannerTask {
+	/// User IDs of the task's assignees.
+	#[must_use]
+	pub fn assignee_ids(&self) -> Vec<&str> {
+		self.assignments.iter().flatten().map(|(user_id, _)| user_id.as_str()).collect()
+	}
+
+	/// The order hint of the assignee shown last, which new assignees go after.
+	#[must_use]
+	pub fn last_assignment_order_hint(&self) -> Option<&str> {
+		self.assignments.iter().flatten().filter_map(|(_, assignment)| assignment.order_hint.as_deref()).max()
+	}
+}
+
+impl PlannerTaskUpdate {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn title(mut self, title: &str) -> Self {
+		self.title = Some(title.to_owned());
+		self
+	}
+
+	#[must_use]
+	pub fn due_date_time(mut self, due_date_time: &str) -> Self {
+		self.due_date_time = Some(Some(due_date_time.to_owned()));
+		self
+	}
+
+	#[must_use]
+	pub fn clear_due_date_time(mut self) -> Self {
+		self.due_date_time = Some(None);
+		self
+	}
+
+	#[must_use]
+	pub const fn percent_complete(mut self, percent_complete: i32) -> Self {
+		self.percent_complete = Some(percent_complete);
+		self
+	}
+
+	#[must_use]
+	pub const fn priority(mut self, priority: i32) -> Self {
+		self.priority = Some(priority);
+		self
+	}
+}
+
+/*
+{
+	"@odata.etag": "W/\"JzEtVGFza0RldGFpbHMgQEBAQEBAQEBAQEBAQEBAWCc=\"",
+	"description": "Measure the showroom before the design review.",
+	"previewType": "checklist",
+	"id": "01gzSlKkIUSUl0DsAAAAAMgAAAAA",
+	"references": {
+		"https%3A//contoso%2Esharepoint%2Ecom/sites/NewYork/Shared Documents/Floor plan%2Epdf": {
+			"@odata.type": "#microsoft.graph.plannerExternalReference",
+			"alias": "Floor plan",
+			"type": "Pdf",
+			"previewPriority": "8585269241124331036",
+			"lastModifiedDateTime": "2023-02-01T16:12:01.1234567Z",
+			"lastModifiedBy": {
+				"user": {
+					"displayName": null,
+					"id": "c258b80f-26fb-435d-8ad5-95f588c953e5"
+				}
+			}
+		}
+	},
+	"checklist": {
+		"5f3e9a4c-1f2b-4d0e-9a7c-3b8d6e2f1a90": {
+			"@odata.type": "#microsoft.graph.plannerChecklistItem",
+			"isChecked": false,
+			"title": "Confirm wall dimensions",
+			"orderHint": "8585269241124331036",
+			"lastModifiedDateTime": "2023-02-01T16:11:44.7654321Z",
+			"lastModifiedBy": {
+				"user": {
+					"displayName": null,
+					"id": "c258b80f-26fb-435d-8ad5-95f588c953e5"
+				}
+			}
+		}
+	}
+}
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannerTaskDetails {
+	#[serde(rename = "@odata.etag")]
+	pub odata_etag: Option<String>,
+	pub id: String,
+	pub description: Option<String>,
+	#[serde(rename = "previewType")]
+	pub preview_type: Option<String>,
+	/// Keyed by the item's GUID.
+	pub checklist: Option<HashMap<String, PlannerChecklistItem>>,
+	/// Keyed by the encoded url, see `PlannerTaskDetails::reference_key`.
+	pub references: Option<HashMap<String, PlannerExternalReference>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannerChecklistItem {
+	#[serde(rename = "@odata.type")]
+	pub odata_type: Option<String>,
+	#[serde(rename = "isChecked")]
+	pub is_checked: Option<bool>,
+	pub title: Option<String>,
+	#[serde(rename = "orderHint")]
+	pub order_hint: Option<String>,
+	#[serde(rename = "lastModifiedDateTime")]
+	pub last_modified_date_time: Option<String>,
+	#[serde(rename = "lastModifiedBy")]
+	pub last_modified_by: Option<CreatedBy>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannerExternalReference {
+	#[serde(rename = "@odata.type")]
+	pub odata_type: Option<String>,
+	pub alias: Option<String>,
+	/// The kind of file, e.g. "Pdf", "Word" or "Other".
+	#[serde(rename = "type")]
+	pub type_: Option<String>,
+	#[serde(rename = "previewPriority")]
+	pub preview_priority: Option<String>,
+	#[serde(rename = "lastModifiedDateTime")]
+	pub last_modified_date_time: Option<String>,
+	#[serde(rename = "lastModifiedBy")]
+	pub last_modified_by: Option<CreatedBy>,
+}
+
+///
+/// Changes to a task's details. Checklist items and references are changed one key at a time, every other entry is left as is.
+///
+/// ```ignore
+/// let details = graph.automation_get_task_details(Identity::App, &task.id).await?;
+/// let update = PlannerTaskDetailsUpdate::new().description("Install the showroom lighting.").add_checklist_items(&["Confirm delivery", "Mount fixtures", "Final walkthrough"]);
+/// graph.automation_update_task_details(Identity::App, &details, &update).await?;
+/// ```
+///
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct PlannerTaskDetailsUpdate {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub description: Option<String>,
+	/// `None` removes the item with that key.
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub checklist: HashMap<String, Option<PlannerChecklistItemUpdate>>,
+	/// Keyed by `PlannerTaskDetails::reference_key`; `None` removes the reference.
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub references: HashMap<String, Option<PlannerExternalReferenceUpdate>>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct PlannerChecklistItemUpdate {
+	#[serde(rename = "@odata.type")]
+	pub odata_type: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub title: Option<String>,
+	#[serde(rename = "isChecked", skip_serializing_if = "Option::is_none")]
+	pub is_checked: Option<bool>,
+	#[serde(rename = "orderHint", skip_serializing_if = "Option::is_none")]
+	pub order_hint: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct PlannerExternalReferenceUpdate {
+	#[serde(rename = "@odata.type")]
+	pub odata_type: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub alias: Option<String>,
+	#[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+	pub type_: Option<String>,
+}
+
+impl PlannerTaskDetails {
+	/// The key Planner stores a reference to `url` under: `%`, `.`, `:`, `@` and `#` percent-encoded.
+	/// (e.g. `https://contoso.com/a.pdf` becomes `https%3A//contoso%2Ecom/a%2Epdf`)
+	#[must_use]
+	pub fn reference_key(url: &str) -> String {
+		url.replace('%', "%25").replace('.', "%2E").replace(':', "%3A").replace('@', "%40").replace('#', "%23")
+	}
+
+	/// The url a reference key stands for.
+	#[must_use]
+	pub fn reference_url(key: &str) -> String {
+		percent_decode_str(key).decode_utf8_lossy().into_owned()
+	}
+
+	/// Checklist items in the order the Planner apps show them.
+	#[must_use]
+	pub fn checklist_items(&self) -> Vec<(&str, &PlannerChecklistItem)> {
+		let mut items = self.checklist.iter().flatten().map(|(key, item)| (key.as_str(), item)).collect::<Vec<_>>();
+		items.sort_by(|(_, a), (_, b)| a.order_hint.cmp(&b.order_hint));
+		items
+	}
+}
+
+impl PlannerTaskDetailsUpdate {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn description(mut self, description: &str) -> Self {
+		self.description = Some(description.to_owned());
+		self
+	}
+
+	/// Add an unchecked item under a new GUID key; Planner picks its `orderHint`.
+	#[must_use]
+	pub fn add_checklist_item(mut self, title: &str) -> Self {
+		self.checklist.insert(new_guid(), Some(PlannerChecklistItemUpdate { title: Some(title.to_owned()), is_checked: Some(false), ..PlannerChecklistItemUpdate::default() }));
+		self
+	}
+
+	/// Add several unchecked items, e.g. a standard checklist for install tasks.
+	#[must_use]
+	pub fn add_checklist_items(self, titles: &[&str]) -> Self {
+		titles.iter().fold(self, |update, title| update.add_checklist_item(title))
+	}
+
+	#[must_use]
+	pub fn check(mut self, key: &str, is_checked: bool) -> Self {
+		self.checklist_entry(key).is_checked = Some(is_checked);
+		self
+	}
+
+	#[must_use]
+	pub fn rename_checklist_item(mut self, key: &str, title: &str) -> Self {
+		self.checklist_entry(key).title = Some(title.to_owned());
+		self
+	}
+
+	#[must_use]
+	pub fn remove_checklist_item(mut self, key: &str) -> Self {
+		self.checklist.insert(key.to_owned(), None);
+		self
+	}
+
+	/// Add or change the reference to `url`. `type_` is the kind of file, e.g. "Pdf", "Word" or "Other".
+	#[must_use]
+	pub fn reference(mut self, url: &str, alias: &str, type_: Option<&str>) -> Self {
+		let reference = PlannerExternalReferenceUpdate { odata_type: "microsoft.graph.plannerExternalReference".to_owned(), alias: Some(alias.to_owned()), type_: type_.map(ToOwned::to_owned) };
+		self.references.insert(PlannerTaskDetails::reference_key(url), Some(reference));
+		self
+	}
+
+	#[must_use]
+	pub fn remove_reference(mut self, url: &str) -> Self {
+		self.references.insert(PlannerTaskDetails::reference_key(url), None);
+		self
+	}
+
+	fn checklist_entry(&mut self, key: &str) -> &mut PlannerChecklistItemUpdate {
+		let entry = self.checklist.entry(key.to_owned()).or_default();
+		entry.get_or_insert_with(PlannerChecklistItemUpdate::default)
+	}
+}
+
+impl Default for PlannerChecklistItemUpdate {
+	fn default() -> Self {
+		Self { odata_type: "microsoft.graph.plannerChecklistItem".to_owned(), title: None, is_checked: None, order_hint: None }
+	}
+}
+
+/// A random (version 4) GUID, as Planner expects for new checklist item keys.
+fn new_guid() -> String {
+	let mut bytes: [u8; 16] = rand::random();
+	bytes[6] = (bytes[6] & 0x0f) | 0x40;
+	bytes[8] = (bytes[8] & 0x3f) | 0x80;
+	let hex = format!("{:032x}", u128::from_be_bytes(bytes));
+	format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
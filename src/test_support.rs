@@ -0,0 +1,388 @@
+//!
+//! An in-process mock of Graph and the Entra ID token endpoint for offline tests, enabled by the `test-support` feature.
+//!
+//! ```ignore
+//! let mock = MockGraph::start().await;
+//! mock.on("GET", "/v1.0/groups/*/team", MockResponse::json(200, &fixtures::team("team-1", "New York")));
+//! let graph = mock.graph().await?;
+//! let team = graph.automation_team_by_group_id(Identity::App, "team-1".to_owned()).await?;
+//! assert_eq!(mock.requests_to("GET", "/v1.0/groups/*/team").len(), 1);
+//! ```
+//!
+
+use std::{
+	fmt::{self, Write},
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use percent_encoding::percent_decode_str;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use tokio::{
+	io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+	net::{TcpListener, TcpStream},
+	task::JoinHandle,
+};
+
+use crate::{AppCredentials, GraphError, MSGraph, MSGraphConfig, RetryPolicy};
+
+/// Tenant ID of the app `MockGraph::graph` signs in as.
+pub const TENANT_ID: &str = "00000000-0000-0000-0000-00000000000a";
+
+/// Client ID of the app `MockGraph::graph` signs in as.
+pub const CLIENT_ID: &str = "00000000-0000-0000-0000-00000000000b";
+
+///
+/// A Graph and login server on a random local port.
+///
+/// Routes are matched latest first, so a route registered later overrides an earlier one until it is used up.
+/// The token endpoint answers with `fixtures::token` unless a test registers its own route for it.
+///
+pub struct MockGraph {
+	url: String,
+	state: Arc<Mutex<MockState>>,
+	server: JoinHandle<()>,
+}
+
+///
+/// A scripted answer of `MockGraph`.
+///
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+	pub status: u16,
+	pub headers: Vec<(String, String)>,
+	pub body: Vec<u8>,
+}
+
+///
+/// A request received by `MockGraph`. `path` excludes the query string; header names are lower case.
+///
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+	pub method: String,
+	pub path: String,
+	pub query: Option<String>,
+	pub headers: Vec<(String, String)>,
+	pub body: Vec<u8>,
+}
+
+type Handler = Box<dyn Fn(&RecordedRequest) -> MockResponse + Send + Sync>;
+
+struct MockRoute {
+	method: String,
+	path: String,
+	handler: Handler,
+	remaining: Option<usize>,
+}
+
+#[derive(Default)]
+struct MockState {
+	routes: Vec<MockRoute>,
+	requests: Vec<RecordedRequest>,
+}
+
+impl MockGraph {
+	/// Start the server.
+	///
+	/// # Panics
+	/// Panics if no local port can be bound.
+	pub async fn start() -> Self {
+		let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind a local port for MockGraph");
+		let url = format!("http://{}", listener.local_addr().expect("local address of MockGraph"));
+		let state = Arc::new(Mutex::new(MockState::default()));
+
+		let server_state = state.clone();
+		let server = tokio::spawn(async move {
+			while let Ok((stream, _)) = listener.accept().await {
+				tokio::spawn(serve(stream, server_state.clone()));
+			}
+		});
+
+		let mock = Self { url, state, server };
+		mock.on("POST", "/*/oauth2/v2.0/token", MockResponse::json(200, &fixtures::token()));
+		mock
+	}
+
+	/// Base url of the server, used as Graph root and authority.
+	#[must_use]
+	pub fn url(&self) -> &str {
+		&self.url
+	}
+
+	/// A config pointing Graph and login at this server, retrying after a few milliseconds instead of seconds.
+	#[must_use]
+	pub fn config(&self) -> MSGraphConfig {
+		MSGraphConfig::default().graph_root(&self.url).authority(&self.url).retry_policy(RetryPolicy::default().base_delay(Duration::from_millis(1)).max_delay(Duration::from_millis(10)))
+	}
+
+	/// An `MSGraph` signed in against this server as `TENANT_ID`/`CLIENT_ID`.
+	///
+	/// # Errors
+	/// Returns the `GraphError` of the token request, e.g. when a test scripted the token endpoint to fail.
+	pub async fn graph(&self) -> Result<MSGraph, GraphError> {
+		MSGraph::with_config(self.config(), &AppCredentials::new(TENANT_ID, CLIENT_ID, "mock-secret")).await
+	}
+
+	/// Answer every `method` request to `path` with `response`. In `path`, `*` matches one segment. (e.g. "/v1.0/teams/*/channels")
+	pub fn on(&self, method: &str, path: &str, response: MockResponse) {
+		self.route(method, path, None, Box::new(move |_| response.clone()));
+	}
+
+	/// Answer the next `times` matching requests with `response`, then fall back to earlier routes.
+	pub fn times(&self, method: &str, path: &str, times: usize, response: MockResponse) {
+		self.route(method, path, Some(times), Box::new(move |_| response.clone()));
+	}
+
+	/// Answer the next matching request with `response`, then fall back to earlier routes.
+	pub fn once(&self, method: &str, path: &str, response: MockResponse) {
+		self.times(method, path, 1, response);
+	}
+
+	/// Answer every matching request with what `handler` builds from it, e.g. depending on `$filter`.
+	pub fn on_fn(&self, method: &str, path: &str, handler: impl Fn(&RecordedRequest) -> MockResponse + Send + Sync + 'static) {
+		self.route(method, path, None, Box::new(handler));
+	}
+
+	fn route(&self, method: &str, path: &str, remaining: Option<usize>, handler: Handler) {
+		lock(&self.state).routes.push(MockRoute { method: method.to_ascii_uppercase(), path: path.to_owned(), handler, remaining });
+	}
+
+	/// Every request received so far, in order.
+	#[must_use]
+	pub fn requests(&self) -> Vec<RecordedRequest> {
+		lock(&self.state).requests.clone()
+	}
+
+	/// The requests received so far that match `method` and the `path` pattern.
+	#[must_use]
+	pub fn requests_to(&self, method: &str, path: &str) -> Vec<RecordedRequest> {
+		self.requests().into_iter().filter(|request| request.method.eq_ignore_ascii_case(method) && matches(path, &request.path)).collect()
+	}
+}
+
+impl Drop for MockGraph {
+	fn drop(&mut self) {
+		self.server.abort();
+	}
+}
+
+impl fmt::Debug for MockGraph {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MockGraph").field("url", &self.url).finish_non_exhaustive()
+	}
+}
+
+impl MockResponse {
+	#[must_use]
+	pub fn json(status: u16, body: &serde_json::Value) -> Self {
+		Self { status, headers: vec![("Content-Type".to_owned(), "application/json".to_owned())], body: body.to_string().into_bytes() }
+	}
+
+	#[must_use]
+	pub fn bytes(status: u16, body: Vec<u8>) -> Self {
+		Self { status, headers: vec![("Content-Type".to_owned(), "application/octet-stream".to_owned())], body }
+	}
+
+	/// An empty response, e.g. 204 for a DELETE.
+	#[must_use]
+	pub const fn status(status: u16) -> Self {
+		Self { status, headers: Vec::new(), body: Vec::new() }
+	}
+
+	/// A failure in Graph's error envelope.
+	#[must_use]
+	pub fn graph_error(status: u16, code: &str, message: &str) -> Self {
+		Self::json(status, &json!({ "error": { "code": code, "message": message, "innerError": { "request-id": "mock-request-id" } } }))
+	}
+
+	/// 429 with a `Retry-After` of `seconds`.
+	#[must_use]
+	pub fn throttled(seconds: u64) -> Self {
+		Self::graph_error(429, "TooManyRequests", "Too many requests.").header("Retry-After", &seconds.to_string())
+	}
+
+	#[must_use]
+	pub fn header(mut self, name: &str, value: &str) -> Self {
+		self.headers.push((name.to_owned(), value.to_owned()));
+		self
+	}
+}
+
+impl RecordedRequest {
+	#[must_use]
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+	}
+
+	/// A decoded query parameter. (e.g. `query_param("$filter")`)
+	#[must_use]
+	pub fn query_param(&self, name: &str) -> Option<String> {
+		self.query.as_deref()?.split('&').filter_map(|pair| pair.split_once('=')).find(|(key, _)| percent_decode_str(key).decode_utf8_lossy() == name).map(|(_, value)| percent_decode_str(&value.replace('+', " ")).decode_utf8_lossy().into_owned())
+	}
+
+	/// The body as JSON.
+	///
+	/// # Panics
+	/// Panics if the body is not a `T`.
+	#[must_use]
+	pub fn json<T: DeserializeOwned>(&self) -> T {
+		serde_json::from_slice(&self.body).unwrap_or_else(|e| panic!("{} {} has no JSON body: {e}", self.method, self.path))
+	}
+
+	/// The body as form parameters, e.g. of a token request.
+	#[must_use]
+	pub fn form(&self) -> Vec<(String, String)> {
+		String::from_utf8_lossy(&self.body).split('&').filter_map(|pair| pair.split_once('=')).map(|(key, value)| (decode_form(key), decode_form(value))).collect()
+	}
+}
+
+fn decode_form(value: &str) -> String {
+	percent_decode_str(&value.replace('+', " ")).decode_utf8_lossy().into_owned()
+}
+
+fn lock(state: &Mutex<MockState>) -> std::sync::MutexGuard<'_, MockState> {
+	state.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Whether `path` matches `pattern`, where a `*` segment matches any one segment.
+fn matches(pattern: &str, path: &str) -> bool {
+	let pattern = pattern.split('/');
+	let path = path.split('/');
+	pattern.clone().count() == path.clone().count() && pattern.zip(path).all(|(expected, actual)| expected == "*" || expected == actual)
+}
+
+fn respond(state: &Mutex<MockState>, request: RecordedRequest) -> MockResponse {
+	let mut state = lock(state);
+	let route = state.routes.iter_mut().rev().find(|route| route.remaining != Some(0) && route.method == request.method && matches(&route.path, &request.path));
+	let response = route.map_or_else(
+		|| MockResponse::graph_error(404, "itemNotFound", &format!("No mock route for {} {}", request.method, request.path)),
+		|route| {
+			route.remaining = route.remaining.map(|remaining| remaining - 1);
+			(route.handler)(&request)
+		},
+	);
+	state.requests.push(request);
+	response
+}
+
+/// Serve HTTP/1.1 requests on `stream` until the client closes it.
+async fn serve(stream: TcpStream, state: Arc<Mutex<MockState>>) {
+	let mut stream = BufReader::new(stream);
+	while let Some(request) = read_request(&mut stream).await {
+		let response = respond(&state, request);
+		let reason = StatusCode::from_u16(response.status).ok().and_then(|status| status.canonical_reason()).unwrap_or("Unknown");
+		let mut head = format!("HTTP/1.1 {} {reason}\r\nContent-Length: {}\r\n", response.status, response.body.len());
+		for (name, value) in &response.headers {
+			let _ = write!(head, "{name}: {value}\r\n");
+		}
+		head.push_str("\r\n");
+		let stream = stream.get_mut();
+		if stream.write_all(head.as_bytes()).await.is_err() || stream.write_all(&response.body).await.is_err() {
+			return;
+		}
+	}
+}
+
+async fn read_request(stream: &mut BufReader<TcpStream>) -> Option<RecordedRequest> {
+	let mut line = String::new();
+	stream.read_line(&mut line).await.ok().filter(|read| *read > 0)?;
+	let mut parts = line.split_whitespace();
+	let method = parts.next()?.to_owned();
+	let target = parts.next()?;
+	let (path, query) = target.split_once('?').map_or_else(|| (target.to_owned(), None), |(path, query)| (path.to_owned(), Some(query.to_owned())));
+
+	let mut headers = Vec::new();
+	loop {
+		let mut line = String::new();
+		stream.read_line(&mut line).await.ok().filter(|read| *read > 0)?;
+		let line = line.trim_end();
+		if line.is_empty() {
+			break;
+		}
+		if let Some((name, value)) = line.split_once(':') {
+			headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+		}
+	}
+
+	let length = headers.iter().find(|(name, _)| name == "content-length").and_then(|(_, value)| value.parse().ok()).unwrap_or(0);
+	let mut body = vec![0; length];
+	stream.read_exact(&mut body).await.ok()?;
+	Some(RecordedRequest { method, path, query, headers, body })
+}
+
+///
+/// Canned Graph objects shaped like real responses, for scripting `MockGraph`.
+///
+pub mod fixtures {
+	use serde_json::{json, Value};
+
+	/// A token endpoint answer valid for an hour.
+	#[must_use]
+	pub fn token() -> Value {
+		json!({ "token_type": "Bearer", "expires_in": 3599, "ext_expires_in": 3599, "access_token": "mock-access-token" })
+	}
+
+	/// One page of a collection; `next_link` makes the client ask for another.
+	#[must_use]
+	pub fn page(values: &[Value], next_link: Option<&str>) -> Value {
+		json!({ "@odata.nextLink": next_link, "value": values })
+	}
+
+	/// A single-page collection.
+	#[must_use]
+	pub fn collection(values: &[Value]) -> Value {
+		page(values, None)
+	}
+
+	#[must_use]
+	pub fn group(id: &str, display_name: &str) -> Value {
+		json!({ "id": id, "displayName": display_name, "mailNickname": display_name.replace(' ', ""), "resourceProvisioningOptions": ["Team"], "groupTypes": ["Unified"] })
+	}
+
+	#[must_use]
+	pub fn team(id: &str, display_name: &str) -> Value {
+		json!({ "id": id, "displayName": display_name, "description": format!("{display_name} Team Site"), "visibility": "private", "isArchived": false })
+	}
+
+	#[must_use]
+	pub fn channel(id: &str, display_name: &str) -> Value {
+		json!({ "id": id, "displayName": display_name, "description": null, "membershipType": "standard", "tenantId": super::TENANT_ID })
+	}
+
+	#[must_use]
+	pub fn plan(id: &str, title: &str, group_id: &str) -> Value {
+		json!({ "@odata.etag": "W/\"mock-etag\"", "id": id, "title": title, "owner": group_id, "container": { "containerId": group_id, "type": "group", "url": format!("https://graph.microsoft.com/beta/groups/{group_id}") } })
+	}
+
+	#[must_use]
+	pub fn bucket(id: &str, name: &str, plan_id: &str) -> Value {
+		json!({ "@odata.etag": "W/\"mock-etag\"", "id": id, "name": name, "planId": plan_id, "orderHint": "8585" })
+	}
+
+	#[must_use]
+	pub fn task(id: &str, title: &str, plan_id: &str, bucket_id: &str) -> Value {
+		json!({ "@odata.etag": "W/\"mock-etag\"", "id": id, "title": title, "planId": plan_id, "bucketId": bucket_id, "orderHint": "8585", "percentComplete": 0, "priority": 5, "dueDateTime": null, "assignments": {} })
+	}
+
+	#[must_use]
+	pub fn task_details(id: &str) -> Value {
+		json!({ "@odata.etag": "W/\"mock-details-etag\"", "id": id, "description": "", "previewType": "automatic", "checklist": {}, "references": {} })
+	}
+
+	#[must_use]
+	pub fn site(id: &str, display_name: &str) -> Value {
+		json!({ "id": id, "displayName": display_name, "name": display_name.replace(' ', ""), "webUrl": format!("https://contoso.sharepoint.com/sites/{}", display_name.replace(' ', "")) })
+	}
+
+	#[must_use]
+	pub fn drive(id: &str, name: &str) -> Value {
+		json!({ "id": id, "name": name, "driveType": "documentLibrary" })
+	}
+
+	#[must_use]
+	pub fn tab(display_name: &str) -> Value {
+		json!({ "displayName": display_name, "configuration": { "entityId": "mock-entity" } })
+	}
+}
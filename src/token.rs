@@ -0,0 +1,166 @@
+use std::{
+	collections::HashMap,
+	future::Future,
+	sync::{Arc, Mutex as StdMutex},
+	time::{Duration, SystemTime},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use eggersmann_app_server_auth::MSAccessToken;
+use openssl::sha::sha256;
+use tokio::sync::Mutex;
+
+use crate::{request, AppCredentials, CachedToken, ClientCredential, GraphError, MSGraphConfig, TokenCacheKey, TokenStore};
+
+/// Used when the token response has no usable `expires_in`; Entra ID issues app tokens for roughly an hour.
+const DEFAULT_LIFETIME: Duration = Duration::from_secs(3599);
+
+///
+/// In-memory tokens by `TokenCacheKey`. Each key has its own lock, so concurrent callers wait for a single refresh of that token only.
+/// Expired tokens nobody is waiting on are dropped whenever a new key is added, so the on-behalf-of tokens of users who left do not pile up.
+///
+#[derive(Default)]
+pub struct TokenCache {
+	entries: StdMutex<HashMap<TokenCacheKey, Arc<Mutex<Option<CachedToken>>>>>,
+	store: Option<Arc<dyn TokenStore>>,
+}
+
+impl TokenCache {
+	pub fn new(store: Option<Arc<dyn TokenStore>>) -> Self {
+		Self { entries: StdMutex::default(), store }
+	}
+
+	fn entry(&self, key: &TokenCacheKey) -> Arc<Mutex<Option<CachedToken>>> {
+		let mut entries = self.entries.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		if let Some(entry) = entries.get(key) {
+			return entry.clone();
+		}
+
+		let now = SystemTime::now();
+		entries.retain(|_, entry| Arc::strong_count(entry) > 1 || entry.try_lock().is_ok_and(|cached| cached.as_ref().is_some_and(|token| token.expires_at > now)));
+		entries.entry(key.clone()).or_default().clone()
+	}
+
+	/// The cached token for `key`, or a new one from `fetch` if there is none or it is about to expire.
+	pub async fn get<F: Future<Output = Result<CachedToken, GraphError>>>(&self, key: &TokenCacheKey, fetch: impl FnOnce() -> F) -> Result<CachedToken, GraphError> {
+		let entry = self.entry(key);
+		let mut cached = entry.lock().await;
+		if let Some(token) = cached.as_ref().filter(|token| token.is_fresh()) {
+			return Ok(token.clone());
+		}
+
+		if let Some(store) = &self.store {
+			if let Some(token) = store.load(key).await.filter(CachedToken::is_fresh) {
+				*cached = Some(token.clone());
+				return Ok(token);
+			}
+		}
+
+		let token = fetch().await?;
+		if let Some(store) = &self.store {
+			store.save(key, &token).await;
+		}
+		*cached = Some(token.clone());
+		drop(cached);
+		Ok(token)
+	}
+
+	/// Replace the token for `key` with a new one from `fetch`; the old token is kept if `fetch` fails.
+	pub async fn refresh<F: Future<Output = Result<CachedToken, GraphError>>>(&self, key: &TokenCacheKey, fetch: impl FnOnce() -> F) -> Result<CachedToken, GraphError> {
+		let entry = self.entry(key);
+		let token = fetch().await?;
+		if let Some(store) = &self.store {
+			store.save(key, &token).await;
+		}
+		*entry.lock().await = Some(token.clone());
+		Ok(token)
+	}
+}
+
+///
+/// The cache key of the app token for `credentials`.
+///
+pub fn app_key(config: &MSGraphConfig, credentials: &AppCredentials) -> TokenCacheKey {
+	TokenCacheKey::new(&credentials.tenant_id, &credentials.client_id, None, &app_scopes(config))
+}
+
+///
+/// The cache key of the token exchanged on behalf of the user holding `assertion`.
+/// Keyed on a hash of the whole assertion rather than its unverified `oid` claim, so a forged token can never be handed another user's cached token.
+///
+pub fn on_behalf_of_key(config: &MSGraphConfig, credentials: &AppCredentials, assertion: &str, scopes: &[&str]) -> TokenCacheKey {
+	TokenCacheKey::new(&credentials.tenant_id, &credentials.client_id, Some(URL_SAFE_NO_PAD.encode(sha256(assertion.as_bytes()))), &on_behalf_of_scopes(config, scopes))
+}
+
+///
+/// A string claim from the payload of the JWT `token`, read without verifying the signature.
+///
+pub fn claim(token: &str, name: &str) -> Option<String> {
+	let payload = URL_SAFE_NO_PAD.decode(token.split('.').nth(1)?).ok()?;
+	let claims = serde_json::from_slice::<serde_json::Value>(&payload).ok()?;
+	claims.get(name)?.as_str().map(ToOwned::to_owned)
+}
+
+fn app_scopes(config: &MSGraphConfig) -> Vec<String> {
+	config.app_scopes.iter().map(|scope| config.scope(scope)).collect()
+}
+
+fn on_behalf_of_scopes(config: &MSGraphConfig, scopes: &[&str]) -> Vec<String> {
+	if scopes.is_empty() {
+		vec![config.default_scope()]
+	} else {
+		scopes.iter().map(|scope| config.scope(scope)).collect()
+	}
+}
+
+///
+/// Request a client-credentials token for `credentials`, signing a fresh client assertion when they hold a certificate.
+///
+pub async fn request_app_token(client: &reqwest::Client, config: &MSGraphConfig, credentials: &AppCredentials) -> Result<CachedToken, GraphError> {
+	let scopes = app_scopes(config).join(" ");
+	let mut params = HashMap::new();
+	params.insert("grant_type", "client_credentials".to_owned());
+	params.insert("scope", scopes);
+	request_token(client, config, credentials, params).await
+}
+
+///
+/// Exchange `assertion`, a user's access token issued to this app, for a Graph token carrying `scopes` in that user's name.
+///
+pub async fn request_on_behalf_of_token(client: &reqwest::Client, config: &MSGraphConfig, credentials: &AppCredentials, assertion: &str, scopes: &[&str]) -> Result<CachedToken, GraphError> {
+	let scopes = on_behalf_of_scopes(config, scopes).join(" ");
+	let mut params = HashMap::new();
+	params.insert("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer".to_owned());
+	params.insert("requested_token_use", "on_behalf_of".to_owned());
+	params.insert("assertion", assertion.to_owned());
+	params.insert("scope", scopes);
+	request_token(client, config, credentials, params).await
+}
+
+///
+/// Post `params` to the tenant's token endpoint, authenticating as the app with its secret or a client assertion.
+///
+async fn request_token(client: &reqwest::Client, config: &MSGraphConfig, credentials: &AppCredentials, mut params: HashMap<&str, String>) -> Result<CachedToken, GraphError> {
+	let token_url = config.token_url(&credentials.tenant_id);
+	params.insert("client_id", credentials.client_id.clone());
+	match &credentials.credential {
+		ClientCredential::Secret(client_secret) => {
+			params.insert("client_secret", client_secret.clone());
+		}
+		ClientCredential::Certificate(certificate) => {
+			params.insert("client_assertion_type", "urn:ietf:params:oauth:client-assertion-type:jwt-bearer".to_owned());
+			params.insert("client_assertion", certificate.assertion(&credentials.client_id, &token_url)?);
+		}
+	}
+
+	let requested_at = SystemTime::now();
+	let res = request::send(config, client.post(token_url).form(&params)).await?;
+	let url = res.url().to_string();
+	let json = request::json::<serde_json::Value>(res).await?;
+
+	// `expires_in` is seconds from issue; some proxies send it as a string.
+	let lifetime = json.get("expires_in").and_then(|expires_in| expires_in.as_u64().or_else(|| expires_in.as_str().and_then(|s| s.parse().ok()))).map_or(DEFAULT_LIFETIME, Duration::from_secs);
+	let token = serde_json::from_value::<MSAccessToken>(json).map_err(|source| GraphError::Deserialize { url, source })?;
+
+	Ok(CachedToken { token, expires_at: requested_at + lifetime })
+}
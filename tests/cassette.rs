@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+
+use eggersmann_app_server_ms_graph::{
+	test_support::{fixtures, MockGraph, MockResponse, CLIENT_ID, TENANT_ID},
+	AppCredentials, Cassette, GraphError, Identity, MSGraph, MSGraphConfig, ODataQuery, PlannerTask, CASSETTE_TENANT_ID,
+};
+use serde_json::json;
+
+fn scratch_path(name: &str) -> PathBuf {
+	std::env::temp_dir().join(format!("ms-graph-{name}-{}.json", std::process::id()))
+}
+
+fn fixture(name: &str) -> PathBuf {
+	PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/cassettes").join(name)
+}
+
+#[tokio::test]
+async fn records_scrubbed_exchanges_and_replays_them_offline() {
+	let path = scratch_path("record-replay");
+	let mock = MockGraph::start().await;
+	let team = json!({ "id": "team-1", "displayName": "New York", "description": "Ask Jane.Doe@Contoso.com or jane.doe@contoso.com", "webUrl": format!("https://teams.microsoft.com/l/team/x?groupId=team-1&tenantId={TENANT_ID}") });
+	mock.on("GET", "/v1.0/groups/*/team", MockResponse::json(200, &team));
+	let config = mock.config();
+
+	let graph = MSGraph::with_config(config.clone().cassette(Cassette::record(&path)), &AppCredentials::new(TENANT_ID, CLIENT_ID, "mock-secret")).await.unwrap();
+	graph.automation_team_by_group_id(Identity::App, "team-1".to_owned()).await.unwrap();
+	drop(mock);
+
+	let recorded = std::fs::read_to_string(&path).unwrap();
+	for secret in [TENANT_ID, CLIENT_ID, "mock-secret", "mock-access-token", "contoso.com"] {
+		assert!(!recorded.contains(secret), "cassette leaks {secret}:\n{recorded}");
+	}
+	assert!(recorded.contains(CASSETTE_TENANT_ID));
+	assert!(recorded.contains("Ask user1@example.com or user1@example.com"));
+
+	let graph = MSGraph::with_config(config.cassette(Cassette::replay(&path).unwrap()), &AppCredentials::new(TENANT_ID, CLIENT_ID, "another-secret")).await.unwrap();
+	let team = graph.automation_team_by_group_id(Identity::App, "team-1".to_owned()).await.unwrap();
+	assert_eq!(team.display_name.as_deref(), Some("New York"));
+	assert_eq!(team.web_url.unwrap(), format!("https://teams.microsoft.com/l/team/x?groupId=team-1&tenantId={CASSETTE_TENANT_ID}"));
+
+	// Each recorded exchange answers one request.
+	let err = graph.automation_team_by_group_id(Identity::App, "team-1".to_owned()).await.unwrap_err();
+	assert!(matches!(err, GraphError::Cassette { .. }), "{err}");
+	std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn records_graph_errors() {
+	let path = scratch_path("record-error");
+	let mock = MockGraph::start().await;
+	mock.on("GET", "/v1.0/groups/*/team", MockResponse::graph_error(404, "NotFound", "No team found with Group Id missing"));
+	let config = mock.config();
+
+	let graph = MSGraph::with_config(config.clone().cassette(Cassette::record(&path)), &AppCredentials::new(TENANT_ID, CLIENT_ID, "mock-secret")).await.unwrap();
+	graph.automation_team_by_group_id(Identity::App, "missing".to_owned()).await.unwrap_err();
+	drop(mock);
+
+	let graph = MSGraph::with_config(config.cassette(Cassette::replay(&path).unwrap()), &AppCredentials::new(TENANT_ID, CLIENT_ID, "mock-secret")).await.unwrap();
+	let err = graph.automation_team_by_group_id(Identity::App, "missing".to_owned()).await.unwrap_err();
+	assert!(matches!(err, GraphError::Api { code: Some(ref code), .. } if code == "NotFound"), "{err}");
+	std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn replays_recorded_team_and_plan_shapes() {
+	let cassette = Cassette::replay(fixture("team_and_plans.json")).unwrap();
+	let graph = MSGraph::with_config(MSGraphConfig::default().cassette(cassette), &AppCredentials::new(TENANT_ID, CLIENT_ID, "mock-secret")).await.unwrap();
+
+	let team = graph.automation_team_by_group_id(Identity::App, "197b10b2-2113-4009-96e8-ef4fdbf3d4c8".to_owned()).await.unwrap();
+	assert_eq!(team.display_name.as_deref(), Some("New York"));
+	assert_eq!(team.summary.unwrap().members_count, Some(13));
+
+	let plans = graph.automation_plans(Identity::App, "01b95734-9bf9-4015-b065-4d300ddcd1fb".to_owned(), &ODataQuery::new()).await.unwrap();
+	assert_eq!(plans.len(), 1);
+	assert_eq!(plans[0].title.as_deref(), Some("Automation Tasks"));
+	assert_eq!(plans[0].container.as_ref().unwrap().container_id, "01b95734-9bf9-4015-b065-4d300ddcd1fb");
+}
+
+#[tokio::test]
+async fn scrubs_addresses_after_non_ascii_text() {
+	let path = scratch_path("non-ascii");
+	let mock = MockGraph::start().await;
+	mock.on("GET", "/v1.0/groups/*/team", MockResponse::json(200, &json!({ "id": "team-1", "description": "Kontakt:\u{a0}jane@contoso.com, Büro–max@contoso.com" })));
+	let graph = MSGraph::with_config(mock.config().cassette(Cassette::record(&path)), &AppCredentials::new(TENANT_ID, CLIENT_ID, "mock-secret")).await.unwrap();
+
+	graph.automation_team_by_group_id(Identity::App, "team-1".to_owned()).await.unwrap();
+
+	let recorded = std::fs::read_to_string(&path).unwrap();
+	assert!(recorded.contains("Kontakt:\u{a0}user1@example.com, Büro–user2@example.com"), "{recorded}");
+	std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn scrubs_percent_encoded_addresses_in_urls() {
+	let path = scratch_path("encoded-upn");
+	let user_id = "6e7b768e-07e2-4810-8459-485f84f8f204";
+	let mock = MockGraph::start().await;
+	mock.on("GET", "/v1.0/users/*", MockResponse::json(200, &json!({ "id": user_id })));
+	mock.on("PATCH", "/beta/planner/tasks/task-1", MockResponse::json(200, &fixtures::task("task-1", "Measure showroom", "plan-1", "bucket-1")));
+	let config = mock.config();
+	let task: PlannerTask = serde_json::from_value(fixtures::task("task-1", "Measure showroom", "plan-1", "bucket-1")).unwrap();
+
+	let graph = MSGraph::with_config(config.clone().cassette(Cassette::record(&path)), &AppCredentials::new(TENANT_ID, CLIENT_ID, "mock-secret")).await.unwrap();
+	graph.automation_assign_task(Identity::App, &task, &["jane.doe@contoso.com"]).await.unwrap();
+	drop(mock);
+
+	let recorded = std::fs::read_to_string(&path).unwrap();
+	for leak in ["jane", "contoso", "%40"] {
+		assert!(!recorded.contains(leak), "cassette leaks {leak}:\n{recorded}");
+	}
+	assert!(recorded.contains("/v1.0/users/user1@example.com?$select=id"), "{recorded}");
+
+	let graph = MSGraph::with_config(config.cassette(Cassette::replay(&path).unwrap()), &AppCredentials::new(TENANT_ID, CLIENT_ID, "mock-secret")).await.unwrap();
+	graph.automation_assign_task(Identity::App, &task, &["jane.doe@contoso.com"]).await.unwrap();
+	std::fs::remove_file(path).unwrap();
+}
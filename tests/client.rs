@@ -0,0 +1,107 @@
+use eggersmann_app_server_ms_graph::{
+	test_support::{fixtures, MockGraph, MockResponse},
+	GraphError, Identity, ODataQuery, Team,
+};
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn signs_in_once_and_reuses_the_app_token() {
+	let mock = MockGraph::start().await;
+	mock.on("GET", "/v1.0/groups/*/team", MockResponse::json(200, &fixtures::team("team-1", "New York")));
+	let graph = mock.graph().await.unwrap();
+
+	for _ in 0..3 {
+		graph.automation_team_by_group_id(Identity::App, "team-1".to_owned()).await.unwrap();
+	}
+
+	let token_requests = mock.requests_to("POST", "/*/oauth2/v2.0/token");
+	assert_eq!(token_requests.len(), 1);
+	let form = token_requests[0].form();
+	assert!(form.contains(&("grant_type".to_owned(), "client_credentials".to_owned())));
+	assert!(form.contains(&("scope".to_owned(), format!("{}/.default", mock.url()))));
+	for request in mock.requests_to("GET", "/v1.0/groups/*/team") {
+		assert_eq!(request.header("authorization"), Some("Bearer mock-access-token"));
+	}
+}
+
+#[tokio::test]
+async fn follows_next_links_across_pages() {
+	let mock = MockGraph::start().await;
+	let second_page = format!("{}/beta/sites?$skiptoken=2", mock.url());
+	mock.on_fn("GET", "/beta/sites", move |request| if request.query_param("$skiptoken").is_some() { MockResponse::json(200, &fixtures::collection(&[fixtures::site("site-3", "Houston")])) } else { MockResponse::json(200, &fixtures::page(&[fixtures::site("site-1", "New York"), fixtures::site("site-2", "Miami")], Some(&second_page))) });
+	let graph = mock.graph().await.unwrap();
+
+	let sites = graph.sites(Identity::App, &ODataQuery::new().top(2)).await.unwrap();
+
+	assert_eq!(sites.iter().map(|site| site.id.as_str()).collect::<Vec<_>>(), ["site-1", "site-2", "site-3"]);
+	assert_eq!(mock.requests_to("GET", "/beta/sites")[0].query_param("$top").as_deref(), Some("2"));
+}
+
+#[tokio::test]
+async fn retries_throttled_requests() {
+	let mock = MockGraph::start().await;
+	mock.on("GET", "/v1.0/groups/*/team", MockResponse::json(200, &fixtures::team("team-1", "New York")));
+	mock.times("GET", "/v1.0/groups/*/team", 2, MockResponse::throttled(0));
+	let graph = mock.graph().await.unwrap();
+
+	let team: Team = graph.automation_team_by_group_id(Identity::App, "team-1".to_owned()).await.unwrap();
+
+	assert_eq!(team.id, "team-1");
+	assert_eq!(mock.requests_to("GET", "/v1.0/groups/*/team").len(), 3);
+}
+
+#[tokio::test]
+async fn gives_up_when_retry_after_exceeds_max_delay() {
+	let mock = MockGraph::start().await;
+	mock.on("GET", "/v1.0/groups/*/team", MockResponse::json(200, &fixtures::team("team-1", "New York")));
+	mock.once("GET", "/v1.0/groups/*/team", MockResponse::throttled(60));
+	let graph = mock.graph().await.unwrap();
+
+	let err = graph.automation_team_by_group_id(Identity::App, "team-1".to_owned()).await.unwrap_err();
+
+	assert_eq!(err.status(), Some(StatusCode::TOO_MANY_REQUESTS));
+	assert_eq!(mock.requests_to("GET", "/v1.0/groups/*/team").len(), 1);
+}
+
+#[tokio::test]
+async fn decodes_graph_errors() {
+	let mock = MockGraph::start().await;
+	mock.on("GET", "/v1.0/groups/*/team", MockResponse::graph_error(404, "NotFound", "No team found with Group Id missing"));
+	let graph = mock.graph().await.unwrap();
+
+	let err = graph.automation_team_by_group_id(Identity::App, "missing".to_owned()).await.unwrap_err();
+
+	let GraphError::Api { status, code, request_id, .. } = err else { panic!("expected GraphError::Api, got {err}") };
+	assert_eq!(status, StatusCode::NOT_FOUND);
+	assert_eq!(code.as_deref(), Some("NotFound"));
+	assert_eq!(request_id.as_deref(), Some("mock-request-id"));
+}
+
+#[tokio::test]
+async fn reports_ambiguous_names() {
+	let mock = MockGraph::start().await;
+	mock.on("GET", "/beta/groups", MockResponse::json(200, &fixtures::collection(&[fixtures::group("group-1", "Sales"), fixtures::group("group-2", "Sales")])));
+	let graph = mock.graph().await.unwrap();
+
+	let err = graph.automation_team_by_name(Identity::App, "Sales".to_owned()).await.unwrap_err();
+
+	assert!(matches!(err, GraphError::Ambiguous { count: 2, .. }), "{err}");
+	let filter = mock.requests_to("GET", "/beta/groups")[0].query_param("$filter").unwrap();
+	assert_eq!(filter, "(displayName eq 'Sales') and (resourceProvisioningOptions/Any(x:x eq 'Team'))");
+}
+
+#[tokio::test]
+async fn uploads_to_the_drive_root() {
+	let mock = MockGraph::start().await;
+	mock.on("GET", "/beta/sites", MockResponse::json(200, &fixtures::collection(&[fixtures::site("site-1", "New York")])));
+	mock.on("GET", "/beta/sites/site-1/drives", MockResponse::json(200, &fixtures::collection(&[fixtures::drive("drive-1", "Documents")])));
+	mock.on("PUT", "/beta/drives/drive-1/root:/*/content", MockResponse::json(201, &serde_json::json!({ "id": "item-1", "name": "Q1 Report #2.pdf" })));
+	let graph = mock.graph().await.unwrap();
+
+	let item = graph.put_item_in_site_drive(Identity::App, "New York", "Documents", "Q1 Report #2.pdf", b"%PDF".to_vec()).await.unwrap();
+
+	assert!(item.contains("item-1"));
+	let uploads = mock.requests_to("PUT", "/beta/drives/drive-1/root:/*/content");
+	assert_eq!(uploads[0].path, "/beta/drives/drive-1/root:/Q1%20Report%20%232.pdf:/content");
+	assert_eq!(uploads[0].body, b"%PDF");
+}
@@ -0,0 +1,219 @@
+use eggersmann_app_server_ms_graph::{
+	test_support::{fixtures, MockGraph, MockResponse},
+	CreatePlannerTask, GraphError, Identity, ODataQuery, PlannerTask, PlannerTaskDetails, PlannerTaskDetailsUpdate, PlannerTaskUpdate, PRIORITY_URGENT,
+};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+fn task(id: &str) -> PlannerTask {
+	serde_json::from_value(fixtures::task(id, "Measure showroom", "plan-1", "bucket-1")).unwrap()
+}
+
+#[tokio::test]
+async fn creates_a_task_in_a_bucket() {
+	let mock = MockGraph::start().await;
+	mock.on("POST", "/beta/planner/tasks", MockResponse::json(201, &fixtures::task("task-1", "Measure showroom", "plan-1", "bucket-1")));
+	let graph = mock.graph().await.unwrap();
+
+	let task = graph.automation_create_task(Identity::App, &CreatePlannerTask::new("plan-1", "bucket-1", "Measure showroom").due_date_time("2023-02-10T17:00:00Z").priority(PRIORITY_URGENT)).await.unwrap();
+
+	assert_eq!(task.id, "task-1");
+	let body: Value = mock.requests_to("POST", "/beta/planner/tasks")[0].json();
+	assert_eq!(body, json!({ "planId": "plan-1", "bucketId": "bucket-1", "title": "Measure showroom", "dueDateTime": "2023-02-10T17:00:00Z", "priority": 1 }));
+}
+
+#[tokio::test]
+async fn lists_tasks_by_plan_and_bucket() {
+	let mock = MockGraph::start().await;
+	mock.on("GET", "/beta/planner/plans/plan-1/tasks", MockResponse::json(200, &fixtures::collection(&[fixtures::task("task-1", "Measure", "plan-1", "bucket-1"), fixtures::task("task-2", "Install", "plan-1", "bucket-2")])));
+	mock.on("GET", "/beta/planner/buckets/bucket-2/tasks", MockResponse::json(200, &fixtures::collection(&[fixtures::task("task-2", "Install", "plan-1", "bucket-2")])));
+	let graph = mock.graph().await.unwrap();
+
+	let plan_tasks = graph.automation_plan_tasks(Identity::App, "plan-1".to_owned(), &ODataQuery::new()).await.unwrap();
+	let bucket_tasks = graph.automation_bucket_tasks(Identity::App, "bucket-2".to_owned(), &ODataQuery::new()).await.unwrap();
+
+	assert_eq!(plan_tasks.len(), 2);
+	assert_eq!(bucket_tasks[0].bucket_id.as_deref(), Some("bucket-2"));
+}
+
+#[tokio::test]
+async fn updates_send_the_etag() {
+	let mock = MockGraph::start().await;
+	mock.on_fn("PATCH", "/beta/planner/tasks/task-1", |request| {
+		let mut task = fixtures::task("task-1", "Measure showroom", "plan-1", "bucket-1");
+		task["percentComplete"] = request.json::<Value>()["percentComplete"].clone();
+		MockResponse::json(200, &task)
+	});
+	let graph = mock.graph().await.unwrap();
+
+	let update = PlannerTaskUpdate::new().title("Measure showroom").percent_complete(50).clear_due_date_time();
+	let updated = graph.automation_update_task(Identity::App, &task("task-1"), &update).await.unwrap();
+
+	assert_eq!(updated.percent_complete, Some(50));
+	let request = &mock.requests_to("PATCH", "/beta/planner/tasks/task-1")[0];
+	assert_eq!(request.header("if-match"), Some("W/\"mock-etag\""));
+	assert_eq!(request.header("prefer"), Some("return=representation"));
+	assert_eq!(request.json::<Value>(), json!({ "title": "Measure showroom", "dueDateTime": null, "percentComplete": 50 }));
+}
+
+#[tokio::test]
+async fn outdated_updates_fail_with_412() {
+	let mock = MockGraph::start().await;
+	mock.on("PATCH", "/beta/planner/tasks/task-1", MockResponse::graph_error(412, "", "The If-Match header contains an invalid value."));
+	let graph = mock.graph().await.unwrap();
+
+	let err = graph.automation_update_task(Identity::App, &task("task-1"), &PlannerTaskUpdate::new().priority(3)).await.unwrap_err();
+
+	assert_eq!(err.status(), Some(StatusCode::PRECONDITION_FAILED));
+}
+
+#[tokio::test]
+async fn deletes_need_an_etag() {
+	let mock = MockGraph::start().await;
+	mock.on("DELETE", "/beta/planner/tasks/task-1", MockResponse::status(204));
+	let graph = mock.graph().await.unwrap();
+
+	graph.automation_delete_task(Identity::App, &task("task-1")).await.unwrap();
+	assert_eq!(mock.requests_to("DELETE", "/beta/planner/tasks/task-1")[0].header("if-match"), Some("W/\"mock-etag\""));
+
+	let without_etag = PlannerTask { odata_etag: None, ..task("task-1") };
+	let err = graph.automation_delete_task(Identity::App, &without_etag).await.unwrap_err();
+	assert!(matches!(err, GraphError::MissingField(_)), "{err}");
+	assert_eq!(mock.requests_to("DELETE", "/beta/planner/tasks/task-1").len(), 1);
+}
+
+#[tokio::test]
+async fn patches_task_details_with_their_etag() {
+	let mock = MockGraph::start().await;
+	let mut details = fixtures::task_details("task-1");
+	details["checklist"] = json!({ "5f3e9a4c-1f2b-4d0e-9a7c-3b8d6e2f1a90": { "@odata.type": "#microsoft.graph.plannerChecklistItem", "isChecked": false, "title": "Confirm wall dimensions", "orderHint": "8585" } });
+	mock.on("GET", "/beta/planner/tasks/task-1/details", MockResponse::json(200, &details));
+	mock.on("PATCH", "/beta/planner/tasks/task-1/details", MockResponse::json(200, &fixtures::task_details("task-1")));
+	let graph = mock.graph().await.unwrap();
+
+	let details = graph.automation_get_task_details(Identity::App, "task-1").await.unwrap();
+	let (existing, _) = details.checklist_items()[0];
+	let update = PlannerTaskDetailsUpdate::new().description("Install the showroom lighting.").add_checklist_items(&["Confirm delivery", "Mount fixtures"]).check(existing, true).reference("https://contoso.sharepoint.com/sites/NewYork/Floor plan.pdf", "Floor plan", Some("Pdf")).remove_reference("https://contoso.com/old.docx");
+	graph.automation_update_task_details(Identity::App, &details, &update).await.unwrap();
+
+	let request = &mock.requests_to("PATCH", "/beta/planner/tasks/task-1/details")[0];
+	assert_eq!(request.header("if-match"), Some("W/\"mock-details-etag\""));
+	let body: Value = request.json();
+	assert_eq!(body["description"], "Install the showroom lighting.");
+
+	let checklist = body["checklist"].as_object().unwrap();
+	assert_eq!(checklist.len(), 3);
+	assert_eq!(checklist[existing], json!({ "@odata.type": "microsoft.graph.plannerChecklistItem", "isChecked": true }));
+	let mut added = checklist.iter().filter(|(key, _)| key.as_str() != existing).map(|(key, item)| (key.len(), item["title"].as_str().unwrap(), item["isChecked"].as_bool())).collect::<Vec<_>>();
+	added.sort_unstable();
+	assert_eq!(added, [(36, "Confirm delivery", Some(false)), (36, "Mount fixtures", Some(false))]);
+
+	let references = body["references"].as_object().unwrap();
+	assert_eq!(references["https%3A//contoso%2Esharepoint%2Ecom/sites/NewYork/Floor plan%2Epdf"], json!({ "@odata.type": "microsoft.graph.plannerExternalReference", "alias": "Floor plan", "type": "Pdf" }));
+	assert_eq!(references["https%3A//contoso%2Ecom/old%2Edocx"], Value::Null);
+}
+
+#[test]
+fn reference_keys_round_trip() {
+	let url = "https://contoso.com/files/100%.pdf#page=2";
+	let key = PlannerTaskDetails::reference_key(url);
+	assert_eq!(key, "https%3A//contoso%2Ecom/files/100%25%2Epdf%23page=2");
+	assert_eq!(PlannerTaskDetails::reference_url(&key), url);
+}
+
+const OWNER_ID: &str = "c258b80f-26fb-435d-8ad5-95f588c953e5";
+const MEMBER_ID: &str = "6e7b768e-07e2-4810-8459-485f84f8f204";
+
+#[tokio::test]
+async fn assigns_tasks_by_id_and_user_principal_name() {
+	let mock = MockGraph::start().await;
+	mock.on("GET", "/v1.0/users/*", MockResponse::json(200, &json!({ "id": MEMBER_ID })));
+	mock.on("PATCH", "/beta/planner/tasks/task-1", MockResponse::json(200, &fixtures::task("task-1", "Measure showroom", "plan-1", "bucket-1")));
+	let graph = mock.graph().await.unwrap();
+
+	graph.automation_assign_task(Identity::App, &task("task-1"), &[OWNER_ID, "jane.doe@contoso.com"]).await.unwrap();
+
+	let lookup = &mock.requests_to("GET", "/v1.0/users/*")[0];
+	assert_eq!(lookup.path, "/v1.0/users/jane.doe%40contoso.com");
+	assert_eq!(lookup.query_param("$select").as_deref(), Some("id"));
+	let request = &mock.requests_to("PATCH", "/beta/planner/tasks/task-1")[0];
+	assert_eq!(request.header("if-match"), Some("W/\"mock-etag\""));
+	let assignment = |order_hint: &str| json!({ "@odata.type": "#microsoft.graph.plannerAssignment", "orderHint": order_hint });
+	assert_eq!(request.json::<Value>(), json!({ "assignments": { OWNER_ID: assignment(" !"), MEMBER_ID: assignment(" ! !") } }));
+}
+
+#[tokio::test]
+async fn assigns_after_the_current_assignees_in_the_given_order() {
+	let mock = MockGraph::start().await;
+	let mut assigned = fixtures::task("task-1", "Measure showroom", "plan-1", "bucket-1");
+	assigned["assignments"] = json!({
+		OWNER_ID: { "@odata.type": "#microsoft.graph.plannerAssignment", "orderHint": "8585" },
+		"user-0": { "@odata.type": "#microsoft.graph.plannerAssignment", "orderHint": "8584" },
+	});
+	mock.on("PATCH", "/beta/planner/tasks/task-1", MockResponse::json(200, &fixtures::task("task-1", "Measure showroom", "plan-1", "bucket-1")));
+	let graph = mock.graph().await.unwrap();
+
+	let task: PlannerTask = serde_json::from_value(assigned).unwrap();
+	graph.automation_assign_task(Identity::App, &task, &[MEMBER_ID, OWNER_ID, "0f1d4c8e-3a2b-4c5d-9e6f-7a8b9c0d1e2f"]).await.unwrap();
+
+	let body: Value = mock.requests_to("PATCH", "/beta/planner/tasks/task-1")[0].json();
+	let assignments = body["assignments"].as_object().unwrap();
+	assert!(!assignments.contains_key(OWNER_ID));
+	assert_eq!(assignments[MEMBER_ID]["orderHint"], "8585 !");
+	assert_eq!(assignments["0f1d4c8e-3a2b-4c5d-9e6f-7a8b9c0d1e2f"]["orderHint"], "8585 ! !");
+}
+
+#[tokio::test]
+async fn unassigns_tasks() {
+	let mock = MockGraph::start().await;
+	let mut assigned = fixtures::task("task-1", "Measure showroom", "plan-1", "bucket-1");
+	assigned["assignments"] = json!({ OWNER_ID: { "@odata.type": "#microsoft.graph.plannerAssignment", "assignedDateTime": "2023-02-01T16:10:27Z", "orderHint": "8585", "assignedBy": { "user": { "displayName": null, "id": OWNER_ID } } } });
+	mock.on("PATCH", "/beta/planner/tasks/task-1", MockResponse::json(200, &fixtures::task("task-1", "Measure showroom", "plan-1", "bucket-1")));
+	let graph = mock.graph().await.unwrap();
+
+	let task: PlannerTask = serde_json::from_value(assigned).unwrap();
+	assert_eq!(task.assignee_ids(), [OWNER_ID]);
+	let task = graph.automation_unassign_task(Identity::App, &task, &[OWNER_ID]).await.unwrap();
+
+	assert!(task.assignee_ids().is_empty());
+	assert_eq!(mock.requests_to("PATCH", "/beta/planner/tasks/task-1")[0].json::<Value>(), json!({ "assignments": { OWNER_ID: null } }));
+	assert!(mock.requests_to("GET", "/v1.0/users/*").is_empty());
+}
+
+#[tokio::test]
+async fn creates_assigned_tasks() {
+	let mock = MockGraph::start().await;
+	mock.on("POST", "/beta/planner/tasks", MockResponse::json(201, &fixtures::task("task-1", "Install lighting", "plan-1", "bucket-1")));
+	let graph = mock.graph().await.unwrap();
+
+	graph.automation_create_task(Identity::App, &CreatePlannerTask::new("plan-1", "bucket-1", "Install lighting").assignee(OWNER_ID).assignee(MEMBER_ID)).await.unwrap();
+
+	let body: Value = mock.requests_to("POST", "/beta/planner/tasks")[0].json();
+	let mut assignees = body["assignments"].as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+	assignees.sort();
+	assert_eq!(assignees, [MEMBER_ID, OWNER_ID]);
+	assert!(body.get("assignees").is_none());
+}
+
+#[tokio::test]
+async fn retries_throttled_batch_parts() {
+	let mock = MockGraph::start().await;
+	let throttled = std::sync::atomic::AtomicBool::new(false);
+	mock.on_fn("POST", "/beta/$batch", move |request| {
+		let batch: Value = request.json();
+		let responses = batch["requests"].as_array().unwrap().iter().map(|part| match part["id"].as_str().unwrap() {
+			"1" if !throttled.swap(true, std::sync::atomic::Ordering::SeqCst) => json!({ "id": "1", "status": 429, "headers": { "Retry-After": "0" }, "body": { "error": { "code": "TooManyRequests", "message": "Slow down." } } }),
+			id => json!({ "id": id, "status": 201, "body": fixtures::bucket(&format!("bucket-{id}"), part["body"]["name"].as_str().unwrap(), "plan-1") }),
+		});
+		MockResponse::json(200, &json!({ "responses": responses.collect::<Vec<_>>() }))
+	});
+	let graph = mock.graph().await.unwrap();
+
+	let buckets = graph.automation_add_buckets_to_plan(Identity::App, "plan-1".to_owned(), vec!["Tech Work".to_owned(), "Design Work".to_owned(), "Install Work".to_owned()]).await.unwrap();
+
+	assert_eq!(buckets.iter().map(|bucket| bucket.name.as_deref().unwrap()).collect::<Vec<_>>(), ["Tech Work", "Design Work", "Install Work"]);
+	let batches = mock.requests_to("POST", "/beta/$batch");
+	assert_eq!(batches.len(), 2);
+	let resent: Value = batches[1].json();
+	assert_eq!(resent["requests"].as_array().unwrap().iter().map(|part| part["id"].as_str().unwrap()).collect::<Vec<_>>(), ["1"]);
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "routes")]
+
+use eggersmann_app_server_ms_graph::routes::{Upload, UPLOAD_LIMIT};
+use rocket::{
+	data::{Limits, ToByteUnit},
+	http::Status,
+	local::asynchronous::Client,
+	Build, Config, Rocket,
+};
+
+/// Echo the size of the body `Upload` accepted.
+#[rocket::put("/", data = "<upload>")]
+fn upload(upload: Upload) -> String {
+	upload.0.len().to_string()
+}
+
+fn server(limits: Limits) -> Rocket<Build> {
+	rocket::custom(Config::figment().merge(("limits", limits))).mount("/", rocket::routes![upload])
+}
+
+#[tokio::test]
+async fn accepts_uploads_beyond_rockets_file_limit() {
+	let client = Client::tracked(server(Limits::default())).await.unwrap();
+	let body = vec![0_u8; 2 * 1024 * 1024];
+
+	let res = client.put("/").body(&body).dispatch().await;
+
+	assert_eq!(res.status(), Status::Ok);
+	assert_eq!(res.into_string().await.as_deref(), Some("2097152"));
+}
+
+#[tokio::test]
+async fn rejects_uploads_over_the_configured_limit() {
+	let client = Client::tracked(server(Limits::default().limit(UPLOAD_LIMIT, 1.mebibytes()))).await.unwrap();
+
+	let res = client.put("/").body(vec![0_u8; 2 * 1024 * 1024]).dispatch().await;
+
+	assert_eq!(res.status(), Status::PayloadTooLarge);
+}
@@ -0,0 +1,92 @@
+use eggersmann_app_server_ms_graph::{
+	test_support::{fixtures, MockGraph, MockResponse},
+	CreatePlanForm, CreateSharedChannelForm, Identity,
+};
+use serde_json::{json, Value};
+
+/// Script every call `automation_teams_create_shared_channel` makes for team "New York".
+fn script(mock: &MockGraph) {
+	mock.on("GET", "/beta/groups", MockResponse::json(200, &fixtures::collection(&[fixtures::group("group-1", "New York")])));
+	mock.on("GET", "/v1.0/groups/group-1/team", MockResponse::json(200, &fixtures::team("group-1", "New York")));
+	mock.on("POST", "/v1.0/teams/group-1/channels", MockResponse::json(201, &fixtures::channel("channel-1", "Showroom")));
+	mock.on_fn("GET", "/v1.0/teams/group-1/channels", |request| match request.query_param("$filter").as_deref() {
+		Some("displayName eq 'Showroom'") => MockResponse::json(200, &fixtures::collection(&[fixtures::channel("channel-1", "Showroom")])),
+		Some("displayName eq 'General'") => MockResponse::json(200, &fixtures::collection(&[fixtures::channel("channel-0", "General")])),
+		_ => MockResponse::json(200, &fixtures::collection(&[])),
+	});
+	mock.on("POST", "/v1.0/teams/group-1/channels/channel-1/members", MockResponse::json(201, &json!({})));
+	mock.on("POST", "/beta/planner/plans", MockResponse::json(201, &fixtures::plan("plan-1", "Showroom Tasks", "group-1")));
+	mock.on_fn("POST", "/beta/$batch", |request| {
+		let batch: Value = request.json();
+		let responses = batch["requests"].as_array().unwrap().iter().map(|part| json!({ "id": part["id"], "status": 201, "body": fixtures::bucket(&format!("bucket-{}", part["id"].as_str().unwrap()), part["body"]["name"].as_str().unwrap(), "plan-1") })).collect::<Vec<_>>();
+		MockResponse::json(200, &json!({ "responses": responses }))
+	});
+	mock.on("POST", "/beta/teams/group-1/installedApps", MockResponse::graph_error(409, "Conflict", "App is already installed."));
+	mock.on("POST", "/v1.0/teams/group-1/channels/channel-1/tabs", MockResponse::json(201, &fixtures::tab("Showroom Tasks")));
+}
+
+fn form(plan: Option<CreatePlanForm>) -> CreateSharedChannelForm {
+	CreateSharedChannelForm {
+		team_name: "New York".to_owned(),
+		channel_display_name: "Showroom".to_owned(),
+		channel_description: "Showroom projects".to_owned(),
+		owner_id: "owner-1".to_owned(),
+		member_id: "member-1".to_owned(),
+		plan,
+	}
+}
+
+#[tokio::test]
+async fn creates_a_shared_channel_with_a_project_plan() {
+	let mock = MockGraph::start().await;
+	script(&mock);
+	let graph = mock.graph().await.unwrap();
+
+	let plan = CreatePlanForm { plan_name: "Showroom Tasks".to_owned(), plan_template: "Project".to_owned() };
+	let (team, channel, general) = graph.automation_teams_create_shared_channel(Identity::App, &form(Some(plan))).await.unwrap();
+
+	assert_eq!(team.id, "group-1");
+	assert_eq!(channel.id.as_deref(), Some("channel-1"));
+	assert_eq!(general.id.as_deref(), Some("channel-0"));
+
+	let created: Value = mock.requests_to("POST", "/v1.0/teams/group-1/channels")[0].json();
+	assert_eq!(created["membershipType"], "shared");
+	assert_eq!(created["members"][0]["roles"], json!(["owner"]));
+	assert_eq!(created["members"][0]["user@odata.bind"], format!("{}/v1.0/users('owner-1')", mock.url()));
+
+	let member: Value = mock.requests_to("POST", "/v1.0/teams/group-1/channels/channel-1/members")[0].json();
+	assert_eq!(member["user@odata.bind"], format!("{}/v1.0/users('member-1')", mock.url()));
+
+	let batch: Value = mock.requests_to("POST", "/beta/$batch")[0].json();
+	let mut buckets = batch["requests"].as_array().unwrap().iter().map(|part| part["body"]["name"].as_str().unwrap().to_owned()).collect::<Vec<_>>();
+	buckets.sort();
+	assert_eq!(buckets, ["Design Work", "Install Work", "Tech Work"]);
+
+	let tab: Value = mock.requests_to("POST", "/v1.0/teams/group-1/channels/channel-1/tabs")[0].json();
+	assert_eq!(tab["displayName"], "Showroom Tasks");
+	assert_eq!(tab["configuration"]["entityId"], "tt.c_channel-1_p_plan-1");
+}
+
+#[tokio::test]
+async fn creates_a_shared_channel_without_a_plan() {
+	let mock = MockGraph::start().await;
+	script(&mock);
+	let graph = mock.graph().await.unwrap();
+
+	graph.automation_teams_create_shared_channel(Identity::App, &form(None)).await.unwrap();
+
+	assert!(mock.requests_to("POST", "/beta/planner/plans").is_empty());
+	assert!(mock.requests_to("POST", "/beta/$batch").is_empty());
+}
+
+#[tokio::test]
+async fn stops_when_the_team_does_not_exist() {
+	let mock = MockGraph::start().await;
+	mock.on("GET", "/beta/groups", MockResponse::json(200, &fixtures::collection(&[])));
+	let graph = mock.graph().await.unwrap();
+
+	let err = graph.automation_teams_create_shared_channel(Identity::App, &form(None)).await.unwrap_err();
+
+	assert_eq!(err.to_string(), "No matching group New York found.");
+	assert!(mock.requests_to("POST", "/v1.0/teams/*/channels").is_empty());
+}